@@ -1,36 +1,55 @@
-use axum::{Router, routing::get};
+use std::net::SocketAddr;
+
+use axum::{Router, middleware, routing::get};
 use bzd_lib::{error::Error, settings::Settings as _};
 use tracing::info;
 
-use crate::app::{settings::AppSettings, state::AppState};
+use crate::app::{settings::AppSettings, state::AppState, telemetry::Telemetry};
 
+mod admin;
 mod auth;
+mod client_ip;
 mod contacts;
+mod deadline;
 mod error;
+mod grpc;
+mod health;
+mod idempotency_key;
 mod json;
 mod messages;
+mod metrics;
+mod path;
+mod phone_number;
+mod query;
+mod rate_limit;
+mod request_id;
 mod settings;
+mod shutdown;
 mod sources;
 mod state;
+mod telemetry;
 mod topics;
 mod user;
 mod users;
 
 pub async fn run() -> Result<(), Error> {
     let settings = AppSettings::new()?;
+    let telemetry = Telemetry::init(&settings.telemetry)?;
+    metrics::init()?;
     let state = AppState::new(settings).await?;
 
-    http(&state).await?;
+    tokio::try_join!(http(&state), admin(&state))?;
+
+    telemetry.shutdown()?;
 
     Ok(())
 }
 
 async fn http(state: &AppState) -> Result<(), Error> {
-    let router = Router::new()
+    let api = Router::new()
         .nest(
             "/api",
             Router::new()
-                .route("/healthz", get(|| async {}))
                 .nest("/auth", auth::router())
                 .nest("/contacts", contacts::router())
                 .nest("/topics", topics::router())
@@ -38,12 +57,59 @@ async fn http(state: &AppState) -> Result<(), Error> {
                 .nest("/users", users::router())
                 .nest("/messages", messages::router()),
         )
+        .layer(middleware::from_fn_with_state(
+            state.to_owned(),
+            deadline::middleware,
+        ))
+        .layer(middleware::from_fn(idempotency_key::middleware))
+        .layer(middleware::from_fn_with_state(
+            state.to_owned(),
+            rate_limit::middleware,
+        ));
+
+    /*
+    Пробы kubelet ходят с одного адреса, и под лимитом 429 на /livez перезапустил бы под
+     */
+    let router = Router::new()
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
+        .merge(api)
+        .layer(middleware::from_fn(metrics::middleware))
+        .layer(middleware::from_fn(telemetry::middleware))
+        .layer(middleware::from_fn(request_id::middleware))
+        .layer(middleware::from_fn_with_state(
+            state.to_owned(),
+            shutdown::middleware,
+        ))
         .with_state(state.to_owned());
 
     let listener = tokio::net::TcpListener::bind(&state.settings.http.endpoint).await?;
 
     info!("app: started on {}", listener.local_addr()?);
-    axum::serve(listener, router).await?;
+    let server = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(state.shutdown.clone().wait());
+
+    state
+        .shutdown
+        .clone()
+        .drain(server, state.settings.shutdown.drain_timeout)
+        .await
+}
+
+async fn admin(state: &AppState) -> Result<(), Error> {
+    let router = admin::router(state)
+        .layer(middleware::from_fn(request_id::middleware))
+        .with_state(state.to_owned());
+
+    let listener = tokio::net::TcpListener::bind(&state.settings.admin.endpoint).await?;
+
+    info!("admin: started on {}", listener.local_addr()?);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(state.shutdown.clone().wait())
+        .await?;
 
     Ok(())
 }
@@ -0,0 +1,150 @@
+pub mod settings;
+
+use axum::{
+    Router,
+    extract::{Request, State},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use jsonwebtoken::get_current_timestamp;
+
+use crate::app::{error::AppError, json::AppJson, metrics, state::AppState};
+
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/metrics", get(metrics::render))
+        .route("/revocations", post(create_revocation))
+        .route("/upstreams", get(get_upstreams))
+        .layer(middleware::from_fn_with_state(state.to_owned(), authorize))
+}
+
+async fn authorize(
+    State(AppState { settings, .. }): State<AppState>,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    match bearer {
+        Some(TypedHeader(Authorization(bearer)))
+            if constant_time_eq(bearer.token().as_bytes(), settings.admin.token.as_bytes()) => {}
+        _ => return Err(AppError::Unauthorized),
+    }
+
+    Ok(next.run(req).await)
+}
+
+/*
+Сравниваем все байты без раннего выхода, чтобы время ответа не подсказывало префикс токена
+ */
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn create_revocation(
+    State(AppState {
+        revocations,
+        tokens,
+        settings,
+        ..
+    }): State<AppState>,
+    AppJson(req): AppJson<create_revocation::Request>,
+) -> Result<AppJson<create_revocation::Response>, AppError> {
+    let now = get_current_timestamp();
+    let expires_at = now + settings.auth.revocation.max_token_ttl.as_secs();
+
+    match req {
+        create_revocation::Request {
+            jti: Some(jti),
+            expires_at: token_expires_at,
+            ..
+        } => {
+            revocations
+                .revoke_token(&jti, token_expires_at.unwrap_or(expires_at))
+                .await?
+        }
+        create_revocation::Request {
+            user_id: Some(user_id),
+            issued_before,
+            ..
+        } => {
+            let issued_before = issued_before.unwrap_or(now);
+
+            revocations
+                .revoke_user(&user_id, issued_before, expires_at)
+                .await?;
+
+            if let Some(tokens) = tokens {
+                tokens.revoke_user(&user_id, issued_before).await?;
+            }
+        }
+        _ => return Err(AppError::Common),
+    }
+
+    Ok(AppJson(create_revocation::Response {}))
+}
+
+async fn get_upstreams(
+    State(AppState { upstreams, .. }): State<AppState>,
+) -> Result<AppJson<get_upstreams::Response>, AppError> {
+    Ok(AppJson(upstreams.as_ref().into()))
+}
+
+mod create_revocation {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize)]
+    pub struct Request {
+        pub jti: Option<String>,
+        pub expires_at: Option<u64>,
+        pub user_id: Option<String>,
+        pub issued_before: Option<u64>,
+    }
+
+    #[derive(Serialize)]
+    pub struct Response {}
+}
+
+mod get_upstreams {
+    use serde::Serialize;
+
+    use crate::app::grpc::upstream::{Upstream, Upstreams};
+
+    #[derive(Serialize)]
+    pub struct Response {
+        pub upstreams: Vec<UpstreamResponse>,
+    }
+
+    impl From<&Upstreams> for Response {
+        fn from(upstreams: &Upstreams) -> Self {
+            Self {
+                upstreams: upstreams.iter().map(Into::into).collect(),
+            }
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct UpstreamResponse {
+        pub name: &'static str,
+        pub endpoints: Vec<String>,
+        pub discovery: bool,
+        pub tls: bool,
+        pub circuit: &'static str,
+    }
+
+    impl From<&Upstream> for UpstreamResponse {
+        fn from(upstream: &Upstream) -> Self {
+            Self {
+                name: upstream.name,
+                endpoints: upstream.endpoints(),
+                discovery: upstream.is_discovery(),
+                tls: upstream.is_tls(),
+                circuit: upstream.breaker.state(),
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct AdminSettings {
+    pub endpoint: String,
+    pub token: String,
+}
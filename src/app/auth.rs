@@ -1,4 +1,8 @@
+pub mod guard;
+pub mod keys;
+pub mod revocations;
 pub mod settings;
+pub mod tokens;
 
 use axum::{
     Router,
@@ -6,25 +10,47 @@ use axum::{
     routing::{get, post},
 };
 use bzd_users_api::GetUserRequest;
-
-use crate::app::{error::AppError, json::AppJson, state::AppState, user::AppUser};
+use tracing::{error, warn};
+
+use crate::app::{
+    auth::guard::is_failed_attempt,
+    client_ip::ClientIp,
+    error::AppError,
+    json::AppJson,
+    phone_number,
+    state::AppState,
+    user::{AppUser, decode_claims},
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/join", post(join))
         .route("/complete", post(complete))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
         .route("/me", get(me))
 }
 
 async fn join(
     State(AppState {
         auth_service_client,
+        guard,
+        settings,
         ..
     }): State<AppState>,
-    AppJson(data): AppJson<join::Request>,
+    ClientIp(ip): ClientIp,
+    AppJson(mut data): AppJson<join::Request>,
 ) -> Result<AppJson<join::Response>, AppError> {
+    data.phone_number = phone_number::normalize(
+        "phone_number",
+        &data.phone_number,
+        &settings.auth.phone_number,
+    )?;
+
     let request: bzd_users_api::JoinRequest = data.try_into()?;
 
+    guard.check_join(request.phone_number(), ip)?;
+
     let response = auth_service_client
         .clone()
         .join(request)
@@ -88,19 +114,61 @@ mod join {
 async fn complete(
     State(AppState {
         auth_service_client,
+        decoding_keys,
+        tokens,
+        guard,
+        settings,
         ..
     }): State<AppState>,
+    ClientIp(ip): ClientIp,
     AppJson(data): AppJson<complete::Request>,
 ) -> Result<AppJson<complete::Response>, AppError> {
+    let verification_id = data.verification_id.clone();
+
+    let attempt = guard.check_complete(&verification_id, ip)?;
+
     let request: bzd_users_api::CompleteRequest = data.into();
 
-    let response = auth_service_client
-        .clone()
-        .complete(request)
-        .await?
-        .into_inner();
+    let response = match auth_service_client.clone().complete(request).await {
+        Ok(response) => {
+            attempt.succeeded();
+
+            response.into_inner()
+        }
+        Err(status) => {
+            if is_failed_attempt(&status) {
+                attempt.failed();
+            }
+
+            return Err(status.into());
+        }
+    };
 
-    Ok(AppJson(response.into()))
+    /*
+    JWT выпустил апстрим, и вход уже состоялся: если разобрать токен или сохранить
+    refresh-токен не вышло, отдаём JWT как есть, просто без refresh-токена
+     */
+    let refresh = match tokens {
+        Some(tokens) => match decode_claims(response.jwt(), &decoding_keys, &settings.auth) {
+            Ok(claims) => match tokens.issue_refresh_token(&claims.sub).await {
+                Ok(refresh_token) => Some((claims.exp, refresh_token)),
+                Err(err) => {
+                    error!("auth: refresh token not issued: {}", err);
+                    None
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "auth: refresh token not issued, upstream jwt rejected: {}",
+                    err
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(AppJson((response, refresh).into()))
 }
 
 mod complete {
@@ -127,17 +195,99 @@ mod complete {
     #[derive(Serialize)]
     pub struct Response {
         pub jwt: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub expires_at: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub refresh_token: Option<String>,
     }
 
-    impl From<CompleteResponse> for Response {
-        fn from(res: CompleteResponse) -> Self {
+    impl From<(CompleteResponse, Option<(u64, String)>)> for Response {
+        fn from((res, refresh): (CompleteResponse, Option<(u64, String)>)) -> Self {
+            let (expires_at, refresh_token) = refresh.unzip();
+
             Self {
                 jwt: res.jwt().into(),
+                expires_at,
+                refresh_token,
             }
         }
     }
 }
 
+async fn refresh(
+    State(AppState { tokens, .. }): State<AppState>,
+    AppJson(req): AppJson<refresh::Request>,
+) -> Result<AppJson<refresh::Response>, AppError> {
+    let tokens = tokens.ok_or(AppError::NotFound)?;
+
+    Ok(AppJson(tokens.refresh(&req.refresh_token).await?.into()))
+}
+
+mod refresh {
+    use serde::{Deserialize, Serialize};
+
+    use crate::app::auth::tokens::Issued;
+
+    #[derive(Deserialize)]
+    pub struct Request {
+        pub refresh_token: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct Response {
+        pub jwt: String,
+        pub expires_at: u64,
+        pub refresh_token: String,
+    }
+
+    impl From<Issued> for Response {
+        fn from(issued: Issued) -> Self {
+            Self {
+                jwt: issued.jwt,
+                expires_at: issued.expires_at,
+                refresh_token: issued.refresh_token,
+            }
+        }
+    }
+}
+
+async fn logout(
+    State(AppState {
+        tokens,
+        revocations,
+        ..
+    }): State<AppState>,
+    user: Option<AppUser>,
+    AppJson(req): AppJson<logout::Request>,
+) -> Result<AppJson<logout::Response>, AppError> {
+    if let Some(tokens) = tokens {
+        tokens.revoke(&req.refresh_token).await?;
+    }
+
+    if let Some(AppUser {
+        jti: Some(jti),
+        expires_at,
+        ..
+    }) = user
+    {
+        revocations.revoke_token(&jti, expires_at).await?;
+    }
+
+    Ok(AppJson(logout::Response {}))
+}
+
+mod logout {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize)]
+    pub struct Request {
+        pub refresh_token: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct Response {}
+}
+
 async fn me(
     State(AppState {
         users_service_client,
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::app::{auth::settings::GuardSettings, error::AppError};
+
+pub struct Guard {
+    settings: GuardSettings,
+    windows: Mutex<HashMap<String, Window>>,
+    lockouts: Mutex<HashMap<String, Lockout>>,
+}
+
+struct Window {
+    started_at: Instant,
+    count: u32,
+}
+
+struct Lockout {
+    failures: u32,
+    pending: u32,
+    locked_until: Instant,
+}
+
+/*
+Попытка complete резервируется до похода в апстрим: параллельные догадки по одному
+verification_id не должны проскакивать проверку, пока первая ещё не вернулась
+ */
+pub struct Attempt<'a> {
+    guard: &'a Guard,
+    verification_id: String,
+    settled: bool,
+}
+
+impl Guard {
+    pub fn new(settings: GuardSettings) -> Arc<Self> {
+        let guard = Arc::new(Self {
+            settings,
+            windows: Mutex::new(HashMap::new()),
+            lockouts: Mutex::new(HashMap::new()),
+        });
+
+        tokio::spawn({
+            let window = guard.settings.window;
+            let guard = Arc::downgrade(&guard);
+
+            async move {
+                let mut interval = tokio::time::interval(window);
+
+                loop {
+                    interval.tick().await;
+
+                    let Some(guard) = guard.upgrade() else {
+                        break;
+                    };
+
+                    guard.evict(Instant::now());
+                }
+            }
+        });
+
+        guard
+    }
+
+    pub fn check_join(&self, phone_number: i64, ip: IpAddr) -> Result<(), AppError> {
+        self.hit(&[
+            (
+                format!("join:phone_number:{phone_number}"),
+                self.settings.join_per_phone_number,
+            ),
+            (format!("join:ip:{ip}"), self.settings.join_per_ip),
+        ])
+    }
+
+    /*
+    Первые complete_max_failures попыток могут идти параллельно, дальше — по одной
+    и не чаще, чем позволяет текущая блокировка
+     */
+    pub fn check_complete(
+        &self,
+        verification_id: &str,
+        ip: IpAddr,
+    ) -> Result<Attempt<'_>, AppError> {
+        let now = Instant::now();
+
+        let mut lockouts = self.lockouts.lock().map_err(|_| AppError::Internal)?;
+
+        let lockout = lockouts.get(verification_id);
+
+        if let Some(lockout) = lockout {
+            if lockout.locked_until > now {
+                return Err(AppError::TooManyRequests(lockout.locked_until - now));
+            }
+
+            if lockout.pending > 0
+                && lockout.failures + lockout.pending >= self.settings.complete_max_failures
+            {
+                return Err(AppError::TooManyRequests(self.settings.lockout));
+            }
+        }
+
+        self.hit(&[(format!("complete:ip:{ip}"), self.settings.complete_per_ip)])?;
+
+        lockouts
+            .entry(verification_id.into())
+            .or_insert(Lockout {
+                failures: 0,
+                pending: 0,
+                locked_until: now,
+            })
+            .pending += 1;
+
+        Ok(Attempt {
+            guard: self,
+            verification_id: verification_id.into(),
+            settled: false,
+        })
+    }
+
+    fn hit(&self, keys: &[(String, u32)]) -> Result<(), AppError> {
+        let now = Instant::now();
+
+        let mut windows = self.windows.lock().map_err(|_| AppError::Internal)?;
+
+        for (key, limit) in keys {
+            let Some(window) = windows.get(key) else {
+                continue;
+            };
+
+            let elapsed = now.duration_since(window.started_at);
+
+            if elapsed < self.settings.window && window.count >= *limit {
+                return Err(AppError::TooManyRequests(self.settings.window - elapsed));
+            }
+        }
+
+        for (key, _) in keys {
+            let window = windows.entry(key.clone()).or_insert(Window {
+                started_at: now,
+                count: 0,
+            });
+
+            if now.duration_since(window.started_at) >= self.settings.window {
+                window.started_at = now;
+                window.count = 0;
+            }
+
+            window.count += 1;
+        }
+
+        Ok(())
+    }
+
+    fn evict(&self, now: Instant) {
+        if let Ok(mut windows) = self.windows.lock() {
+            windows.retain(|_, it| now.duration_since(it.started_at) < self.settings.window);
+        }
+
+        if let Ok(mut lockouts) = self.lockouts.lock() {
+            lockouts.retain(|_, it| {
+                it.pending > 0
+                    || it.locked_until > now
+                    || now.duration_since(it.locked_until) < self.settings.max_lockout
+            });
+        }
+    }
+}
+
+impl Attempt<'_> {
+    /*
+    Первые complete_max_failures ошибок бесплатные, дальше блокируем verification_id
+    на lockout * 2^n, но не дольше max_lockout
+     */
+    pub fn failed(mut self) {
+        self.settled = true;
+
+        let Ok(mut lockouts) = self.guard.lockouts.lock() else {
+            return;
+        };
+
+        let Some(lockout) = lockouts.get_mut(&self.verification_id) else {
+            return;
+        };
+
+        let settings = &self.guard.settings;
+
+        lockout.pending = lockout.pending.saturating_sub(1);
+        lockout.failures += 1;
+
+        if let Some(n) = lockout.failures.checked_sub(settings.complete_max_failures) {
+            let duration = settings
+                .lockout
+                .saturating_mul(2u32.saturating_pow(n))
+                .min(settings.max_lockout);
+
+            lockout.locked_until = Instant::now() + duration;
+        }
+    }
+
+    pub fn succeeded(mut self) {
+        self.settled = true;
+
+        if let Ok(mut lockouts) = self.guard.lockouts.lock() {
+            lockouts.remove(&self.verification_id);
+        }
+    }
+}
+
+/*
+Апстрим не ответил по существу (недоступен, отвалился клиент) — попытку не засчитываем,
+просто освобождаем резерв
+ */
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+
+        if let Ok(mut lockouts) = self.guard.lockouts.lock()
+            && let Some(lockout) = lockouts.get_mut(&self.verification_id)
+        {
+            lockout.pending = lockout.pending.saturating_sub(1);
+        }
+    }
+}
+
+pub fn is_failed_attempt(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::InvalidArgument
+            | tonic::Code::NotFound
+            | tonic::Code::PermissionDenied
+            | tonic::Code::Unauthenticated
+            | tonic::Code::FailedPrecondition
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::IpAddr, sync::Mutex, time::Duration};
+
+    use super::Guard;
+    use crate::app::{auth::settings::GuardSettings, error::AppError};
+
+    fn guard() -> Guard {
+        Guard {
+            settings: GuardSettings {
+                window: Duration::from_secs(60),
+                join_per_phone_number: 1,
+                join_per_ip: 1,
+                complete_per_ip: 100,
+                complete_max_failures: 2,
+                lockout: Duration::from_secs(30),
+                max_lockout: Duration::from_secs(3600),
+            },
+            windows: Mutex::new(HashMap::new()),
+            lockouts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn join_rejected_by_ip_does_not_count_phone_number() {
+        let guard = guard();
+
+        guard.check_join(1, IP).unwrap();
+
+        assert!(matches!(
+            guard.check_join(2, IP),
+            Err(AppError::TooManyRequests(_))
+        ));
+        assert!(guard.check_join(2, "10.0.0.1".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn concurrent_attempts_are_reserved() {
+        let guard = guard();
+
+        let first = guard.check_complete("v", IP).unwrap();
+        let second = guard.check_complete("v", IP).unwrap();
+
+        assert!(guard.check_complete("v", IP).is_err());
+
+        first.failed();
+        second.failed();
+
+        assert!(guard.check_complete("v", IP).is_err());
+    }
+
+    #[test]
+    fn dropped_attempt_releases_reservation() {
+        let guard = guard();
+
+        let first = guard.check_complete("v", IP).unwrap();
+        let second = guard.check_complete("v", IP).unwrap();
+
+        drop(first);
+        drop(second);
+
+        guard.check_complete("v", IP).unwrap().succeeded();
+
+        assert!(guard.lockouts.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use bzd_lib::error::Error;
+use config::ConfigError;
+use jsonwebtoken::{DecodingKey, jwk::JwkSet};
+use notify::{RecursiveMode, Watcher as _};
+use tokio::{fs, sync::mpsc};
+use tracing::{info, warn};
+
+use crate::app::{
+    auth::settings::{AuthSettings, JwksSettings},
+    error::AppError,
+    metrics,
+};
+
+type Jwks = HashMap<String, Arc<DecodingKey>>;
+
+pub struct DecodingKeys {
+    file: RwLock<FileKeys>,
+    jwks: RwLock<Arc<Jwks>>,
+}
+
+#[derive(Default)]
+struct FileKeys {
+    pem: Vec<u8>,
+    current: Option<Arc<DecodingKey>>,
+    previous: Option<(Arc<DecodingKey>, Instant)>,
+}
+
+impl DecodingKeys {
+    pub async fn new(settings: &AuthSettings) -> Result<Arc<Self>, Error> {
+        let file = match &settings.public_key_file {
+            Some(public_key_file) => {
+                let (pem, key) = load_file(public_key_file).await?;
+
+                FileKeys {
+                    pem,
+                    current: Some(Arc::new(key)),
+                    previous: None,
+                }
+            }
+            None => FileKeys::default(),
+        };
+
+        let jwks = match &settings.jwks {
+            Some(jwks) => load_jwks(jwks).await?,
+            None => Jwks::new(),
+        };
+
+        if file.current.is_none() && jwks.is_empty() {
+            return Err(
+                ConfigError::Message("auth: public_key_file or jwks is required".into()).into(),
+            );
+        }
+
+        let keys = Arc::new(Self {
+            file: RwLock::new(file),
+            jwks: RwLock::new(Arc::new(jwks)),
+        });
+
+        if let Some(jwks) = settings.jwks.clone() {
+            tokio::spawn(refresh_jwks(keys.clone(), jwks));
+        }
+
+        if let Some(public_key_file) = settings.public_key_file.clone() {
+            watch_file(keys.clone(), public_key_file, settings.public_key_grace)?;
+        }
+
+        Ok(keys)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        let file = self.file.read().is_ok_and(|it| it.current.is_some());
+        let jwks = self.jwks.read().is_ok_and(|it| !it.is_empty());
+
+        file || jwks
+    }
+
+    /*
+    Если kid есть и он нам известен — проверяем только этим ключом,
+    иначе пробуем ключ из public_key_file (bzd-users пока подписывает без kid),
+    а в течение public_key_grace после ротации ещё и предыдущий
+     */
+    pub fn find(&self, kid: Option<&str>) -> Vec<Arc<DecodingKey>> {
+        let jwks = self.jwks.read().map(|it| it.clone()).unwrap_or_default();
+
+        if let Some(key) = kid.and_then(|kid| jwks.get(kid)) {
+            return vec![key.clone()];
+        }
+
+        let Ok(file) = self.file.read() else {
+            return vec![];
+        };
+
+        let previous = file
+            .previous
+            .as_ref()
+            .filter(|(_, until)| Instant::now() < *until)
+            .map(|(key, _)| key.clone());
+
+        file.current.iter().cloned().chain(previous).collect()
+    }
+
+    async fn reload_file(&self, public_key_file: &str, grace: Duration) -> Result<(), Error> {
+        let (pem, key) = load_file(public_key_file).await?;
+
+        let Ok(mut file) = self.file.write() else {
+            return Err(AppError::Internal.into());
+        };
+
+        if file.pem == pem {
+            return Ok(());
+        }
+
+        let previous = file.current.replace(Arc::new(key));
+
+        file.pem = pem;
+        file.previous = previous.map(|it| (it, Instant::now() + grace));
+
+        info!("keys: public key reloaded from {}", public_key_file);
+        metrics::count_key_reload("file", true);
+
+        Ok(())
+    }
+}
+
+async fn load_file(public_key_file: &str) -> Result<(Vec<u8>, DecodingKey), Error> {
+    let pem = fs::read_to_string(public_key_file).await?.into_bytes();
+    let key = DecodingKey::from_rsa_pem(&pem).map_err(|_| AppError::Internal)?;
+
+    Ok((pem, key))
+}
+
+/*
+Следим за директорией, а не за самим файлом: в k8s секрет обновляется подменой симлинка ..data,
+и события на самом файле мы не получим
+ */
+fn watch_file(
+    keys: Arc<DecodingKeys>,
+    public_key_file: String,
+    grace: Duration,
+) -> Result<(), Error> {
+    let (tx, mut rx) = mpsc::channel(1);
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if res.is_ok() {
+            let _ = tx.try_send(());
+        }
+    })?;
+
+    let dir = match Path::new(&public_key_file).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => Path::new(".").to_path_buf(),
+    };
+
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            if let Err(err) = keys.reload_file(&public_key_file, grace).await {
+                warn!("keys: reload failed, keeping previous key: {:?}", err);
+                metrics::count_key_reload("file", false);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn load_jwks(settings: &JwksSettings) -> Result<Jwks, Error> {
+    let jwk_set: JwkSet =
+        if settings.source.starts_with("http://") || settings.source.starts_with("https://") {
+            reqwest::get(&settings.source)
+                .await?
+                .error_for_status()?
+                .json()
+                .await?
+        } else {
+            serde_json::from_str(&fs::read_to_string(&settings.source).await?)?
+        };
+
+    let mut jwks = Jwks::new();
+
+    for jwk in &jwk_set.keys {
+        let Some(kid) = &jwk.common.key_id else {
+            warn!("jwks: skip key without kid");
+            continue;
+        };
+
+        /*
+        Один битый ключ в наборе не должен лишать нас остальных
+         */
+        match DecodingKey::from_jwk(jwk) {
+            Ok(key) => {
+                jwks.insert(kid.clone(), Arc::new(key));
+            }
+            Err(err) => warn!("jwks: skip key {}: {}", kid, err),
+        }
+    }
+
+    Ok(jwks)
+}
+
+async fn refresh_jwks(keys: Arc<DecodingKeys>, settings: JwksSettings) {
+    let mut interval = tokio::time::interval(settings.refresh_interval);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        match load_jwks(&settings).await {
+            Ok(jwks) => {
+                info!("jwks: refreshed {} keys", jwks.len());
+                metrics::count_key_reload("jwks", true);
+
+                if let Ok(mut it) = keys.jwks.write() {
+                    *it = Arc::new(jwks);
+                }
+            }
+            Err(err) => {
+                warn!("jwks: refresh failed, keeping previous keys: {:?}", err);
+                metrics::count_key_reload("jwks", false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, RwLock},
+        time::Duration,
+    };
+
+    use serde_json::json;
+
+    use super::{DecodingKeys, FileKeys, load_file, load_jwks};
+    use crate::app::auth::settings::JwksSettings;
+
+    const PUBLIC_KEY_A: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA7gJCPu/lUzYT5vT+Ii7S
+FDtPzWnqR341g3Kq+nvbyatjHBEwrXVuXJxU6HdJ2h9IPg9oDCsMwr84h+qvG2bK
+oGIWrtfMV472wz1uNcQ7RMDPbU0AmfbL/RYgx7uXkFQ6mp/+v4XMTphqqBrY88ch
+5T96VIyVqQdPgKF+3qMj/qCKGcqLM5IMBr91xqDVpHahBvRKooTCMUcQvSkm+fVW
+H3sXjHUvRabYrAc56zUt2OW3uIqkC62W6Hq0HVCkiK2cDOsf80cRsLpftdLzcUzF
+r9/HKYayf4xbo7XTUdPDlG0XVh/PAYCzarx7FStnUDc640RZjeUf5pbYthLK3wPg
+zwIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    const PUBLIC_KEY_B: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAwlv3mB7bND5Wg4iBEEj3
+iOJZvqEGdmTsHhJqrgQn6H1cPbUekzsBuFR2T/nncZj4Unt1zBsoOzOngUDxdpyu
+9T0bm3EyHpooo7NxOSUd8xz2iB9d6uCQ7SmrkV7g58rfzBc0+h9kcj2ByXhaVlmV
+JR7y3BekikdNVgkry7eIn48lGvi87uzuqeuHNwad1VKvvSjQilDj/Cb0OuZiAcgZ
+EV8Ls/SYf06tL4mE/kZVEZfMk2k8JKbflGFaDYAPLujSkt1nmqDb7JDI9XlEnC81
+8Yn5PHqHYRtXxmqyziJRNEVDbjPlDxN2HSILHWt4QJ2HJmtQvvl2L8PQeD6mLRnL
+CwIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    const MODULUS_A: &str = "7gJCPu_lUzYT5vT-Ii7SFDtPzWnqR341g3Kq-nvbyatjHBEwrXVuXJxU6HdJ2h9IPg9oDCsMwr84h-qvG2bKoGIWrtfMV472wz1uNcQ7RMDPbU0AmfbL_RYgx7uXkFQ6mp_-v4XMTphqqBrY88ch5T96VIyVqQdPgKF-3qMj_qCKGcqLM5IMBr91xqDVpHahBvRKooTCMUcQvSkm-fVWH3sXjHUvRabYrAc56zUt2OW3uIqkC62W6Hq0HVCkiK2cDOsf80cRsLpftdLzcUzFr9_HKYayf4xbo7XTUdPDlG0XVh_PAYCzarx7FStnUDc640RZjeUf5pbYthLK3wPgzw";
+
+    const MODULUS_B: &str = "wlv3mB7bND5Wg4iBEEj3iOJZvqEGdmTsHhJqrgQn6H1cPbUekzsBuFR2T_nncZj4Unt1zBsoOzOngUDxdpyu9T0bm3EyHpooo7NxOSUd8xz2iB9d6uCQ7SmrkV7g58rfzBc0-h9kcj2ByXhaVlmVJR7y3BekikdNVgkry7eIn48lGvi87uzuqeuHNwad1VKvvSjQilDj_Cb0OuZiAcgZEV8Ls_SYf06tL4mE_kZVEZfMk2k8JKbflGFaDYAPLujSkt1nmqDb7JDI9XlEnC818Yn5PHqHYRtXxmqyziJRNEVDbjPlDxN2HSILHWt4QJ2HJmtQvvl2L8PQeD6mLRnLCw";
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", uuid::Uuid::new_v4(), name));
+        std::fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    fn rsa_jwk(kid: &str, n: &str) -> serde_json::Value {
+        json!({"kty": "RSA", "alg": "RS256", "use": "sig", "kid": kid, "n": n, "e": "AQAB"})
+    }
+
+    async fn file_keys(public_key_file: &str) -> FileKeys {
+        let (pem, key) = load_file(public_key_file).await.unwrap();
+
+        FileKeys {
+            pem,
+            current: Some(Arc::new(key)),
+            previous: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn selects_jwks_key_by_kid() {
+        let jwks_file = temp_file(
+            "jwks.json",
+            &json!({"keys": [
+                rsa_jwk("a", MODULUS_A),
+                rsa_jwk("b", MODULUS_B),
+                rsa_jwk("broken", "not base64!"),
+            ]})
+            .to_string(),
+        );
+        let public_key_file = temp_file("public.pem", PUBLIC_KEY_A);
+
+        let jwks = load_jwks(&JwksSettings {
+            source: jwks_file.to_string_lossy().into(),
+            refresh_interval: Duration::from_secs(60),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(jwks.len(), 2);
+
+        let keys = DecodingKeys {
+            file: RwLock::new(file_keys(&public_key_file.to_string_lossy()).await),
+            jwks: RwLock::new(Arc::new(jwks.clone())),
+        };
+
+        let found = keys.find(Some("b"));
+        assert_eq!(found.len(), 1);
+        assert!(Arc::ptr_eq(&found[0], &jwks["b"]));
+
+        let fallback = keys.find(Some("unknown"));
+        let file = keys.file.read().unwrap();
+        assert_eq!(fallback.len(), 1);
+        assert!(Arc::ptr_eq(&fallback[0], file.current.as_ref().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn keeps_previous_key_during_grace() {
+        let public_key_file = temp_file("public.pem", PUBLIC_KEY_A);
+        let path = public_key_file.to_string_lossy().to_string();
+
+        let keys = DecodingKeys {
+            file: RwLock::new(file_keys(&path).await),
+            jwks: RwLock::default(),
+        };
+
+        std::fs::write(&public_key_file, PUBLIC_KEY_B).unwrap();
+        keys.reload_file(&path, Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        assert_eq!(keys.find(None).len(), 2);
+
+        std::fs::write(&public_key_file, PUBLIC_KEY_A).unwrap();
+        keys.reload_file(&path, Duration::ZERO).await.unwrap();
+
+        assert_eq!(keys.find(None).len(), 1);
+    }
+}
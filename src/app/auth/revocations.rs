@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use jsonwebtoken::get_current_timestamp;
+
+use crate::app::{error::AppError, user::Claims};
+
+#[async_trait]
+pub trait RevocationStore: Send + Sync {
+    async fn revoke_token(&self, jti: &str, expires_at: u64) -> Result<(), AppError>;
+
+    async fn revoke_user(
+        &self,
+        user_id: &str,
+        issued_before: u64,
+        expires_at: u64,
+    ) -> Result<(), AppError>;
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, AppError>;
+}
+
+#[derive(Default)]
+pub struct MemoryRevocationStore {
+    tokens: Mutex<HashMap<String, u64>>,
+    users: Mutex<HashMap<String, UserRevocation>>,
+}
+
+struct UserRevocation {
+    issued_before: u64,
+    expires_at: u64,
+}
+
+impl MemoryRevocationStore {
+    pub fn new(cleanup_interval: Duration) -> Arc<Self> {
+        let store = Arc::new(Self::default());
+
+        tokio::spawn({
+            let store = Arc::downgrade(&store);
+
+            async move {
+                let mut interval = tokio::time::interval(cleanup_interval);
+
+                loop {
+                    interval.tick().await;
+
+                    let Some(store) = store.upgrade() else {
+                        break;
+                    };
+
+                    store.evict(get_current_timestamp());
+                }
+            }
+        });
+
+        store
+    }
+
+    fn evict(&self, now: u64) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.retain(|_, expires_at| *expires_at > now);
+        }
+
+        if let Ok(mut users) = self.users.lock() {
+            users.retain(|_, it| it.expires_at > now);
+        }
+    }
+}
+
+#[async_trait]
+impl RevocationStore for MemoryRevocationStore {
+    async fn revoke_token(&self, jti: &str, expires_at: u64) -> Result<(), AppError> {
+        self.tokens
+            .lock()
+            .map_err(|_| AppError::Internal)?
+            .insert(jti.into(), expires_at);
+
+        Ok(())
+    }
+
+    async fn revoke_user(
+        &self,
+        user_id: &str,
+        issued_before: u64,
+        expires_at: u64,
+    ) -> Result<(), AppError> {
+        let mut users = self.users.lock().map_err(|_| AppError::Internal)?;
+
+        let revocation = users.entry(user_id.into()).or_insert(UserRevocation {
+            issued_before,
+            expires_at,
+        });
+
+        revocation.issued_before = revocation.issued_before.max(issued_before);
+        revocation.expires_at = revocation.expires_at.max(expires_at);
+
+        Ok(())
+    }
+
+    async fn is_revoked(&self, claims: &Claims) -> Result<bool, AppError> {
+        let now = get_current_timestamp();
+
+        if let Some(jti) = &claims.jti {
+            let tokens = self.tokens.lock().map_err(|_| AppError::Internal)?;
+
+            if tokens.get(jti).is_some_and(|expires_at| *expires_at > now) {
+                return Ok(true);
+            }
+        }
+
+        let users = self.users.lock().map_err(|_| AppError::Internal)?;
+
+        Ok(users
+            .get(&claims.sub)
+            .filter(|it| it.expires_at > now)
+            .is_some_and(|it| claims.iat.is_none_or(|iat| iat < it.issued_before)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::get_current_timestamp;
+
+    use super::{MemoryRevocationStore, RevocationStore as _};
+    use crate::app::user::Claims;
+
+    fn claims(jti: &str, iat: u64) -> Claims {
+        Claims {
+            sub: "user".into(),
+            exp: iat + 60,
+            iat: Some(iat),
+            nbf: None,
+            iss: None,
+            aud: None,
+            jti: Some(jti.into()),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_revoked_jti() {
+        let store = MemoryRevocationStore::default();
+        let now = get_current_timestamp();
+
+        store.revoke_token("revoked", now + 60).await.unwrap();
+
+        assert!(store.is_revoked(&claims("revoked", now)).await.unwrap());
+        assert!(!store.is_revoked(&claims("other", now)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_tokens_issued_before_user_revocation() {
+        let store = MemoryRevocationStore::default();
+        let now = get_current_timestamp();
+
+        store.revoke_user("user", now, now + 60).await.unwrap();
+
+        assert!(store.is_revoked(&claims("old", now - 10)).await.unwrap());
+        assert!(!store.is_revoked(&claims("new", now)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn evicts_expired_revocations() {
+        let store = MemoryRevocationStore::default();
+
+        store.revoke_token("expired", 100).await.unwrap();
+        store.revoke_token("alive", 300).await.unwrap();
+        store.revoke_user("user", 50, 100).await.unwrap();
+
+        store.evict(200);
+
+        let tokens = store.tokens.lock().unwrap();
+        assert!(!tokens.contains_key("expired"));
+        assert!(tokens.contains_key("alive"));
+        assert!(store.users.lock().unwrap().is_empty());
+    }
+}
@@ -1,6 +1,65 @@
+use std::time::Duration;
+
+use phonenumber::country;
 use serde::Deserialize;
 
 #[derive(Deserialize, Clone)]
 pub struct AuthSettings {
-    pub public_key_file: String,
+    pub public_key_file: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub public_key_grace: Duration,
+    pub jwks: Option<JwksSettings>,
+    pub refresh: Option<RefreshSettings>,
+    pub revocation: RevocationSettings,
+    pub phone_number: PhoneNumberSettings,
+    pub guard: GuardSettings,
+    pub issuer: Option<String>,
+    pub audience: Option<Vec<String>>,
+    #[serde(with = "humantime_serde")]
+    pub leeway: Duration,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct JwksSettings {
+    pub source: String,
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RefreshSettings {
+    pub private_key_file: String,
+    pub kid: Option<String>,
+    #[serde(with = "humantime_serde")]
+    pub access_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub refresh_ttl: Duration,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RevocationSettings {
+    #[serde(with = "humantime_serde")]
+    pub max_token_ttl: Duration,
+    #[serde(with = "humantime_serde")]
+    pub cleanup_interval: Duration,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PhoneNumberSettings {
+    pub default_region: country::Id,
+    pub allowed_regions: Option<Vec<country::Id>>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GuardSettings {
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+    pub join_per_phone_number: u32,
+    pub join_per_ip: u32,
+    pub complete_per_ip: u32,
+    pub complete_max_failures: u32,
+    #[serde(with = "humantime_serde")]
+    pub lockout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_lockout: Duration,
 }
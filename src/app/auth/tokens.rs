@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use bzd_lib::error::Error;
+use config::ConfigError;
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode, get_current_timestamp};
+use tokio::fs;
+
+use crate::app::{
+    auth::{
+        keys::DecodingKeys,
+        settings::{AuthSettings, RefreshSettings},
+    },
+    error::AppError,
+    user::{Audience, Claims, decode_claims},
+};
+
+/*
+Хранилище выданных refresh-токенов. Токен одноразовый: take забирает его из хранилища,
+а /refresh кладёт на его место новый
+ */
+#[async_trait]
+pub trait RefreshStore: Send + Sync {
+    async fn insert(&self, refresh_token: &str, token: RefreshToken) -> Result<(), AppError>;
+
+    async fn take(&self, refresh_token: &str) -> Result<Option<RefreshToken>, AppError>;
+
+    async fn revoke(&self, refresh_token: &str) -> Result<(), AppError>;
+
+    async fn revoke_user(&self, user_id: &str, issued_before: u64) -> Result<(), AppError>;
+}
+
+pub struct RefreshToken {
+    pub user_id: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+pub struct Tokens {
+    settings: RefreshSettings,
+    issuer: Option<String>,
+    audience: Option<Vec<String>>,
+    encoding_key: EncodingKey,
+    store: Arc<dyn RefreshStore>,
+}
+
+pub struct Issued {
+    pub jwt: String,
+    pub expires_at: u64,
+    pub refresh_token: String,
+}
+
+impl Tokens {
+    pub async fn new(
+        settings: &AuthSettings,
+        decoding_keys: &DecodingKeys,
+        store: Arc<dyn RefreshStore>,
+    ) -> Result<Option<Arc<Self>>, Error> {
+        let Some(refresh) = settings.refresh.clone() else {
+            return Ok(None);
+        };
+
+        let private_key = fs::read_to_string(&refresh.private_key_file)
+            .await?
+            .into_bytes();
+
+        let encoding_key =
+            EncodingKey::from_rsa_pem(&private_key).map_err(|_| AppError::Internal)?;
+
+        let tokens = Self {
+            settings: refresh,
+            issuer: settings.issuer.clone(),
+            audience: settings.audience.clone(),
+            encoding_key,
+            store,
+        };
+
+        /*
+        Ключ подписи должен сходиться с тем, чем мы сами проверяем JWT, иначе каждый
+        выпущенный через /refresh токен упадёт с 401 — лучше не стартовать вовсе
+         */
+        let (jwt, _) = tokens.sign("self-check")?;
+
+        if let Err(err) = decode_claims(&jwt, decoding_keys, settings) {
+            return Err(ConfigError::Message(format!(
+                "auth.refresh: private_key_file does not match auth.public_key_file or auth.jwks: {err}"
+            ))
+            .into());
+        }
+
+        Ok(Some(Arc::new(tokens)))
+    }
+
+    pub async fn issue_refresh_token(&self, user_id: &str) -> Result<String, AppError> {
+        let now = get_current_timestamp();
+        let refresh_token = random_token();
+
+        self.store
+            .insert(
+                &refresh_token,
+                RefreshToken {
+                    user_id: user_id.into(),
+                    issued_at: now,
+                    expires_at: now + self.settings.refresh_ttl.as_secs(),
+                },
+            )
+            .await?;
+
+        Ok(refresh_token)
+    }
+
+    pub async fn refresh(&self, refresh_token: &str) -> Result<Issued, AppError> {
+        let RefreshToken {
+            user_id,
+            expires_at,
+            ..
+        } = self
+            .store
+            .take(refresh_token)
+            .await?
+            .ok_or(AppError::InvalidRefreshToken)?;
+
+        if expires_at <= get_current_timestamp() {
+            return Err(AppError::InvalidRefreshToken);
+        }
+
+        let (jwt, expires_at) = self.sign(&user_id)?;
+
+        Ok(Issued {
+            jwt,
+            expires_at,
+            refresh_token: self.issue_refresh_token(&user_id).await?,
+        })
+    }
+
+    fn sign(&self, user_id: &str) -> Result<(String, u64), AppError> {
+        let now = get_current_timestamp();
+
+        let claims = Claims {
+            sub: user_id.into(),
+            exp: now + self.settings.access_ttl.as_secs(),
+            iat: Some(now),
+            nbf: None,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone().map(Audience::Many),
+            jti: Some(random_token()),
+        };
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = self.settings.kid.clone();
+
+        Ok((encode(&header, &claims, &self.encoding_key)?, claims.exp))
+    }
+
+    pub async fn revoke(&self, refresh_token: &str) -> Result<(), AppError> {
+        self.store.revoke(refresh_token).await
+    }
+
+    /*
+    Отзыв пользователя должен гасить и refresh-токены, иначе /refresh выпустит
+    свежий JWT с iat = now, который проверку issued_before уже проходит
+     */
+    pub async fn revoke_user(&self, user_id: &str, issued_before: u64) -> Result<(), AppError> {
+        self.store.revoke_user(user_id, issued_before).await
+    }
+}
+
+/*
+ВНИМАНИЕ: токены живут только в памяти процесса. После рестарта или на другой реплике
+клиенту придётся заново пройти join/complete, а отзыв пользователя через админку
+гасит токены лишь на той реплике, куда пришёл запрос. Для нескольких реплик нужна
+общая реализация RefreshStore
+ */
+#[derive(Default)]
+pub struct MemoryRefreshStore {
+    tokens: Mutex<HashMap<String, RefreshToken>>,
+}
+
+#[async_trait]
+impl RefreshStore for MemoryRefreshStore {
+    async fn insert(&self, refresh_token: &str, token: RefreshToken) -> Result<(), AppError> {
+        let mut tokens = self.tokens.lock().map_err(|_| AppError::Internal)?;
+
+        tokens.retain(|_, it| it.expires_at > token.issued_at);
+        tokens.insert(refresh_token.into(), token);
+
+        Ok(())
+    }
+
+    async fn take(&self, refresh_token: &str) -> Result<Option<RefreshToken>, AppError> {
+        Ok(self
+            .tokens
+            .lock()
+            .map_err(|_| AppError::Internal)?
+            .remove(refresh_token))
+    }
+
+    async fn revoke(&self, refresh_token: &str) -> Result<(), AppError> {
+        self.tokens
+            .lock()
+            .map_err(|_| AppError::Internal)?
+            .remove(refresh_token);
+
+        Ok(())
+    }
+
+    async fn revoke_user(&self, user_id: &str, issued_before: u64) -> Result<(), AppError> {
+        self.tokens
+            .lock()
+            .map_err(|_| AppError::Internal)?
+            .retain(|_, it| it.user_id != user_id || it.issued_at >= issued_before);
+
+        Ok(())
+    }
+}
+
+fn random_token() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|it| format!("{it:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use jsonwebtoken::{EncodingKey, get_current_timestamp};
+
+    use super::{MemoryRefreshStore, Tokens};
+    use crate::app::{auth::settings::RefreshSettings, error::AppError};
+
+    fn tokens(store: Arc<MemoryRefreshStore>) -> Tokens {
+        Tokens {
+            settings: RefreshSettings {
+                private_key_file: String::new(),
+                kid: None,
+                access_ttl: Duration::from_secs(60),
+                refresh_ttl: Duration::from_secs(3600),
+            },
+            issuer: None,
+            audience: None,
+            encoding_key: EncodingKey::from_secret(b"test"),
+            store,
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_fails_after_user_revocation() {
+        let store = Arc::new(MemoryRefreshStore::default());
+        let tokens = tokens(store.clone());
+
+        let revoked = tokens.issue_refresh_token("user").await.unwrap();
+        let other = tokens.issue_refresh_token("other").await.unwrap();
+
+        tokens
+            .revoke_user("user", get_current_timestamp() + 1)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            tokens.refresh(&revoked).await,
+            Err(AppError::InvalidRefreshToken)
+        ));
+        assert!(store.tokens.lock().unwrap().contains_key(&other));
+    }
+
+    #[tokio::test]
+    async fn user_revocation_keeps_newer_refresh_tokens() {
+        let store = Arc::new(MemoryRefreshStore::default());
+        let tokens = tokens(store.clone());
+
+        let refresh_token = tokens.issue_refresh_token("user").await.unwrap();
+
+        tokens.revoke_user("user", 0).await.unwrap();
+
+        assert!(store.tokens.lock().unwrap().contains_key(&refresh_token));
+    }
+}
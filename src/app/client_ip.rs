@@ -0,0 +1,81 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::app::{error::AppError, state::AppState};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+pub struct ClientIp(pub IpAddr);
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AppState { settings, .. } = AppState::from_ref(state);
+
+        if settings.proxy.trust_forwarded_for
+            && let Some(ip) = forwarded_for(parts, settings.proxy.trusted_hops)
+        {
+            return Ok(Self(ip));
+        }
+
+        let ConnectInfo(addr) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or(AppError::Internal)?;
+
+        Ok(Self(addr.ip()))
+    }
+}
+
+/*
+Левые записи X-Forwarded-For присылает сам клиент, им верить нельзя: берём адрес,
+который дописал самый дальний из trusted_hops наших прокси, считая справа
+ */
+fn forwarded_for(parts: &Parts, trusted_hops: usize) -> Option<IpAddr> {
+    parts
+        .headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|it| it.to_str().ok())
+        .flat_map(|it| it.split(','))
+        .rev()
+        .nth(trusted_hops.max(1) - 1)?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::forwarded_for;
+
+    fn parts(values: &[&str]) -> axum::http::request::Parts {
+        let mut req = Request::builder();
+
+        for value in values {
+            req = req.header("x-forwarded-for", *value);
+        }
+
+        req.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn takes_address_appended_by_trusted_proxy() {
+        let parts = parts(&["6.6.6.6, 1.1.1.1", "2.2.2.2"]);
+
+        assert_eq!(forwarded_for(&parts, 1), Some("2.2.2.2".parse().unwrap()));
+        assert_eq!(forwarded_for(&parts, 2), Some("1.1.1.1".parse().unwrap()));
+        assert_eq!(forwarded_for(&parts, 4), None);
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::app::state::AppState;
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+pub async fn middleware(
+    State(AppState { settings, .. }): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    scope(settings.deadline.timeout, next.run(req)).await
+}
+
+pub async fn scope<F: Future>(timeout: Duration, f: F) -> F::Output {
+    DEADLINE.scope(Instant::now() + timeout, f).await
+}
+
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|it| it.saturating_duration_since(Instant::now()))
+        .ok()
+}
@@ -1,23 +1,50 @@
-use std::{convert::Infallible, num::ParseIntError};
+use std::{convert::Infallible, num::ParseIntError, time::Duration};
 
 use axum::{
-    http::StatusCode,
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use axum_extra::typed_header::TypedHeaderRejection;
+use serde::Serialize;
 use thiserror::Error;
+use tonic_types::StatusExt as _;
 use tracing::{debug, error};
 
+use crate::app::{json::AppJson, metrics, request_id};
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error(transparent)]
     Status(#[from] tonic::Status),
     #[error(transparent)]
-    Json(#[from] axum::extract::rejection::JsonRejection),
+    Json(#[from] JsonRejection),
+    #[error(transparent)]
+    Query(#[from] QueryRejection),
+    #[error(transparent)]
+    Path(#[from] PathRejection),
     #[error(transparent)]
     Jwt(#[from] jsonwebtoken::errors::Error),
     #[error(transparent)]
     Transport(#[from] tonic::transport::Error),
+    #[error(transparent)]
+    Header(#[from] TypedHeaderRejection),
+    #[error(transparent)]
+    ParseInt(#[from] ParseIntError),
+    #[error("UNKNOWN_KEY")]
+    UnknownKey,
+    #[error("INVALID_REFRESH_TOKEN")]
+    InvalidRefreshToken,
+    #[error("TOKEN_REVOKED")]
+    TokenRevoked,
+    #[error("UNAUTHORIZED")]
+    Unauthorized,
+    #[error("NOT_FOUND")]
+    NotFound,
+    #[error("VALIDATION_FAILED")]
+    Validation(Vec<ErrorDetail>),
+    #[error("TOO_MANY_REQUESTS")]
+    TooManyRequests(Duration),
     #[error("COMMON")]
     Common,
     #[error("INTERNAL")]
@@ -31,36 +58,450 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        debug!("{}", self.to_string());
+        let status = self.status();
+
+        if status.is_server_error() {
+            error!("{}", self.to_string());
+        } else {
+            debug!("{}", self.to_string());
+        }
 
-        let code = match self {
-            AppError::Status(status) => match status.code() {
-                tonic::Code::InvalidArgument => StatusCode::UNPROCESSABLE_ENTITY,
-                tonic::Code::NotFound => StatusCode::NOT_FOUND,
-                _ => StatusCode::BAD_REQUEST,
+        metrics::count_error(self.variant());
+
+        let res = ErrorResponse {
+            error: ErrorBody {
+                code: self.code(),
+                message: self.message(),
+                details: self.details(),
+                request_id: request_id::current(),
             },
-            AppError::Common | AppError::Jwt(_) | AppError::Json(_) => StatusCode::BAD_REQUEST,
-            AppError::Internal | AppError::Transport(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
-        (code, String::from("")).into_response()
+        let mut response = (status, AppJson(res)).into_response();
+
+        if let Some(retry_after) = self.retry_after() {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+            );
+        }
+
+        response
+    }
+}
+
+impl AppError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AppError::Status(status) if is_timeout(status) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Status(status) => http_status(status.code()),
+            AppError::Jwt(_)
+            | AppError::UnknownKey
+            | AppError::InvalidRefreshToken
+            | AppError::TokenRevoked
+            | AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Common
+            | AppError::Json(_)
+            | AppError::Query(_)
+            | AppError::Path(_)
+            | AppError::Header(_)
+            | AppError::ParseInt(_) => StatusCode::BAD_REQUEST,
+            AppError::Internal | AppError::Transport(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::Status(status) if is_timeout(status) => "DEADLINE_EXCEEDED",
+            AppError::Status(status) => grpc_code(status.code()),
+            AppError::Json(_) => "INVALID_JSON",
+            AppError::Query(_) => "INVALID_QUERY",
+            AppError::Path(_) => "INVALID_PATH",
+            AppError::Jwt(err) => jwt_code(err.kind()),
+            AppError::UnknownKey => "TOKEN_UNKNOWN_KEY",
+            AppError::InvalidRefreshToken => "INVALID_REFRESH_TOKEN",
+            AppError::TokenRevoked => "TOKEN_REVOKED",
+            AppError::Unauthorized => "UNAUTHORIZED",
+            AppError::NotFound => "NOT_FOUND",
+            AppError::Validation(_) => "VALIDATION_FAILED",
+            AppError::TooManyRequests(_) => "TOO_MANY_REQUESTS",
+            AppError::Header(_) => "INVALID_HEADER",
+            AppError::ParseInt(_) => "INVALID_NUMBER",
+            AppError::Common => "BAD_REQUEST",
+            AppError::Internal | AppError::Transport(_) => "INTERNAL",
+        }
+    }
+
+    fn variant(&self) -> &'static str {
+        match self {
+            AppError::Status(_) => "Status",
+            AppError::Json(_) => "Json",
+            AppError::Query(_) => "Query",
+            AppError::Path(_) => "Path",
+            AppError::Jwt(_) => "Jwt",
+            AppError::Transport(_) => "Transport",
+            AppError::Header(_) => "Header",
+            AppError::ParseInt(_) => "ParseInt",
+            AppError::UnknownKey => "UnknownKey",
+            AppError::InvalidRefreshToken => "InvalidRefreshToken",
+            AppError::TokenRevoked => "TokenRevoked",
+            AppError::Unauthorized => "Unauthorized",
+            AppError::NotFound => "NotFound",
+            AppError::Validation(_) => "Validation",
+            AppError::TooManyRequests(_) => "TooManyRequests",
+            AppError::Common => "Common",
+            AppError::Internal => "Internal",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            /*
+            Текст внутренних ошибок апстрима наружу не отдаём, оригинал уже ушёл в лог в into_response
+             */
+            AppError::Status(status)
+                if matches!(
+                    status.code(),
+                    tonic::Code::Unknown | tonic::Code::Internal | tonic::Code::DataLoss
+                ) =>
+            {
+                "internal error".into()
+            }
+            AppError::Status(status) => status
+                .get_error_details()
+                .localized_message()
+                .map(|it| it.message.clone())
+                .unwrap_or_else(|| status.message().into()),
+            AppError::Json(rejection) => rejection.body_text(),
+            AppError::Query(rejection) => rejection.body_text(),
+            AppError::Path(rejection) => rejection.body_text(),
+            AppError::Internal | AppError::Transport(_) => "internal error".into(),
+            AppError::Validation(_) => "validation failed".into(),
+            AppError::TooManyRequests(_) => "too many requests".into(),
+            _ => self.to_string(),
+        }
+    }
+
+    fn details(&self) -> Vec<ErrorDetail> {
+        match self {
+            AppError::Status(status) => {
+                let details = status.get_error_details();
+
+                let bad_request = details
+                    .bad_request()
+                    .into_iter()
+                    .flat_map(|it| &it.field_violations)
+                    .map(|it| ErrorDetail {
+                        field: Some(it.field.clone()),
+                        description: it.description.clone(),
+                    });
+
+                let precondition_failure = details
+                    .precondition_failure()
+                    .into_iter()
+                    .flat_map(|it| &it.violations)
+                    .map(|it| ErrorDetail {
+                        field: Some(it.subject.clone()),
+                        description: it.description.clone(),
+                    });
+
+                let quota_failure = details
+                    .quota_failure()
+                    .into_iter()
+                    .flat_map(|it| &it.violations)
+                    .map(|it| ErrorDetail {
+                        field: Some(it.subject.clone()),
+                        description: it.description.clone(),
+                    });
+
+                bad_request
+                    .chain(precondition_failure)
+                    .chain(quota_failure)
+                    .collect()
+            }
+            AppError::Header(rejection) => vec![ErrorDetail {
+                field: Some(rejection.name().to_string()),
+                description: if rejection.is_missing() {
+                    "missing".into()
+                } else {
+                    "invalid".into()
+                },
+            }],
+            AppError::Validation(details) => details.clone(),
+            _ => vec![],
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AppError::Status(status) => status.get_error_details().retry_info()?.retry_delay,
+            AppError::TooManyRequests(retry_after) => Some(*retry_after),
+            _ => None,
+        }
     }
 }
 
-impl From<ParseIntError> for AppError {
-    fn from(_: ParseIntError) -> Self {
-        Self::Common
+/*
+InvalidArgument исторически отдаём как UNPROCESSABLE_ENTITY, остальное — по мотивам grpc-gateway
+ */
+
+fn http_status(code: tonic::Code) -> StatusCode {
+    match code {
+        tonic::Code::Ok => StatusCode::OK,
+        tonic::Code::Cancelled => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+        tonic::Code::InvalidArgument => StatusCode::UNPROCESSABLE_ENTITY,
+        tonic::Code::OutOfRange => StatusCode::BAD_REQUEST,
+        tonic::Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        tonic::Code::PermissionDenied => StatusCode::FORBIDDEN,
+        tonic::Code::NotFound => StatusCode::NOT_FOUND,
+        tonic::Code::AlreadyExists | tonic::Code::Aborted => StatusCode::CONFLICT,
+        tonic::Code::FailedPrecondition => StatusCode::PRECONDITION_FAILED,
+        tonic::Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        tonic::Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        tonic::Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        tonic::Code::Unknown | tonic::Code::Internal | tonic::Code::DataLoss => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
     }
 }
 
+/*
+Истёкший на нашей стороне grpc-timeout tonic отдаёт как Cancelled
+с TimeoutExpired где-то в цепочке source
+ */
+fn is_timeout(status: &tonic::Status) -> bool {
+    let mut source = std::error::Error::source(status);
+
+    while let Some(err) = source {
+        if err.is::<tonic::TimeoutExpired>() {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
+
+fn jwt_code(kind: &jsonwebtoken::errors::ErrorKind) -> &'static str {
+    use jsonwebtoken::errors::ErrorKind;
+
+    match kind {
+        ErrorKind::ExpiredSignature => "TOKEN_EXPIRED",
+        ErrorKind::ImmatureSignature => "TOKEN_NOT_YET_VALID",
+        ErrorKind::InvalidIssuer => "TOKEN_INVALID_ISSUER",
+        ErrorKind::InvalidAudience => "TOKEN_INVALID_AUDIENCE",
+        ErrorKind::InvalidSignature => "TOKEN_INVALID_SIGNATURE",
+        ErrorKind::MissingRequiredClaim(_) => "TOKEN_MISSING_CLAIM",
+        _ => "TOKEN_MALFORMED",
+    }
+}
+
+fn grpc_code(code: tonic::Code) -> &'static str {
+    match code {
+        tonic::Code::Ok => "OK",
+        tonic::Code::Cancelled => "CANCELLED",
+        tonic::Code::Unknown => "UNKNOWN",
+        tonic::Code::InvalidArgument => "INVALID_ARGUMENT",
+        tonic::Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+        tonic::Code::NotFound => "NOT_FOUND",
+        tonic::Code::AlreadyExists => "ALREADY_EXISTS",
+        tonic::Code::PermissionDenied => "PERMISSION_DENIED",
+        tonic::Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+        tonic::Code::FailedPrecondition => "FAILED_PRECONDITION",
+        tonic::Code::Aborted => "ABORTED",
+        tonic::Code::OutOfRange => "OUT_OF_RANGE",
+        tonic::Code::Unimplemented => "UNIMPLEMENTED",
+        tonic::Code::Internal => "INTERNAL",
+        tonic::Code::Unavailable => "UNAVAILABLE",
+        tonic::Code::DataLoss => "DATA_LOSS",
+        tonic::Code::Unauthenticated => "UNAUTHENTICATED",
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<ErrorDetail>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ErrorDetail {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub description: String,
+}
+
 impl From<Infallible> for AppError {
     fn from(_: Infallible) -> Self {
         Self::Internal
     }
 }
 
-impl From<TypedHeaderRejection> for AppError {
-    fn from(_: TypedHeaderRejection) -> Self {
-        Self::Common
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{
+        http::{StatusCode, header},
+        response::IntoResponse as _,
+    };
+    use http_body_util::BodyExt as _;
+    use serde_json::{Value, json};
+    use tonic_types::{
+        ErrorDetails, FieldViolation, PreconditionViolation, QuotaViolation, StatusExt as _,
+    };
+
+    use super::{AppError, http_status};
+
+    #[test]
+    fn maps_every_grpc_code() {
+        let cases = [
+            (tonic::Code::Ok, 200),
+            (tonic::Code::Cancelled, 499),
+            (tonic::Code::Unknown, 500),
+            (tonic::Code::InvalidArgument, 422),
+            (tonic::Code::DeadlineExceeded, 504),
+            (tonic::Code::NotFound, 404),
+            (tonic::Code::AlreadyExists, 409),
+            (tonic::Code::PermissionDenied, 403),
+            (tonic::Code::ResourceExhausted, 429),
+            (tonic::Code::FailedPrecondition, 412),
+            (tonic::Code::Aborted, 409),
+            (tonic::Code::OutOfRange, 400),
+            (tonic::Code::Unimplemented, 501),
+            (tonic::Code::Internal, 500),
+            (tonic::Code::Unavailable, 503),
+            (tonic::Code::DataLoss, 500),
+            (tonic::Code::Unauthenticated, 401),
+        ];
+
+        for (code, status) in cases {
+            assert_eq!(http_status(code).as_u16(), status, "{code:?}");
+        }
+    }
+
+    async fn render(err: AppError) -> (StatusCode, Option<String>, Value) {
+        let res = err.into_response();
+        let status = res.status();
+        let retry_after = res
+            .headers()
+            .get(header::RETRY_AFTER)
+            .map(|it| it.to_str().unwrap().to_string());
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+
+        (status, retry_after, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn decodes_bad_request() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::InvalidArgument,
+            "invalid",
+            ErrorDetails::with_bad_request(vec![FieldViolation::new("title", "too long")]),
+        );
+
+        let (status, _, body) = render(status.into()).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "INVALID_ARGUMENT");
+        assert_eq!(
+            body["error"]["details"],
+            json!([{"field": "title", "description": "too long"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_precondition_failure() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::FailedPrecondition,
+            "precondition",
+            ErrorDetails::with_precondition_failure(vec![PreconditionViolation::new(
+                "TOS",
+                "user",
+                "not accepted",
+            )]),
+        );
+
+        let (status, _, body) = render(status.into()).await;
+
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            body["error"]["details"],
+            json!([{"field": "user", "description": "not accepted"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_quota_failure() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::ResourceExhausted,
+            "quota",
+            ErrorDetails::with_quota_failure(vec![QuotaViolation::new("messages", "daily limit")]),
+        );
+
+        let (status, _, body) = render(status.into()).await;
+
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body["error"]["details"],
+            json!([{"field": "messages", "description": "daily limit"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_localized_message() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::NotFound,
+            "topic not found",
+            ErrorDetails::with_localized_message("ru-RU", "Топик не найден"),
+        );
+
+        let (_, _, body) = render(status.into()).await;
+
+        assert_eq!(body["error"]["message"], "Топик не найден");
+    }
+
+    #[tokio::test]
+    async fn masks_internal_message() {
+        for code in [
+            tonic::Code::Unknown,
+            tonic::Code::Internal,
+            tonic::Code::DataLoss,
+        ] {
+            let status = tonic::Status::new(code, "pq: password authentication failed");
+
+            let (status, _, body) = render(status.into()).await;
+
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(body["error"]["message"], "internal error", "{code:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn sets_retry_after_from_retry_info() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::Unavailable,
+            "unavailable",
+            ErrorDetails::with_retry_info(Some(Duration::from_millis(1500))),
+        );
+
+        let (status, retry_after, _) = render(status.into()).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(retry_after.as_deref(), Some("2"));
     }
 }
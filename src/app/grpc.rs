@@ -0,0 +1,147 @@
+pub mod balance;
+pub mod breaker;
+pub mod retry;
+#[cfg(test)]
+pub mod testing;
+pub mod tls;
+pub mod upstream;
+
+use std::time::Duration;
+
+use axum::http::{HeaderMap, Response, StatusCode};
+use tonic::{
+    metadata::MetadataValue, service::interceptor::InterceptedService, transport::Channel,
+};
+
+use crate::app::{
+    deadline,
+    grpc::{breaker::Breaker, retry::Retry},
+    idempotency_key, request_id,
+    telemetry::grpc::Trace,
+};
+
+const GRPC_STATUS: &str = "grpc-status";
+
+pub type ServiceChannel = InterceptedService<Breaker<Retry<Trace<Channel>>>, Interceptor>;
+
+#[derive(Clone)]
+pub struct Interceptor {
+    timeout: Duration,
+}
+
+impl Interceptor {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+/*
+В апстрим уходит меньшее из таймаута клиента и остатка дедлайна HTTP-запроса,
+чтобы bzd-users и bzd-messages не работали на уже брошенный запрос
+ */
+impl tonic::service::Interceptor for Interceptor {
+    fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        if let Some(value) = request_id::current().and_then(|it| MetadataValue::try_from(it).ok()) {
+            req.metadata_mut().insert(request_id::HEADER, value);
+        }
+
+        if let Some(value) =
+            idempotency_key::current().and_then(|it| MetadataValue::try_from(it).ok())
+        {
+            req.metadata_mut().insert(idempotency_key::HEADER, value);
+        }
+
+        let timeout = match deadline::remaining() {
+            Some(remaining) if remaining.is_zero() => {
+                return Err(tonic::Status::deadline_exceeded(
+                    "request deadline exceeded",
+                ));
+            }
+            Some(remaining) => remaining.min(self.timeout),
+            None => self.timeout,
+        };
+
+        req.set_timeout(timeout);
+
+        Ok(req)
+    }
+}
+
+/*
+Код ответа до чтения тела: grpc-status в заголовках (trailers-only ответ), а без него —
+по HTTP-статусу, как делает tonic, чтобы 503 от прокси считался Unavailable.
+None — обычный 200, статус придёт в трейлерах
+ */
+pub fn response_code<B>(res: &Response<B>) -> Option<tonic::Code> {
+    status_code(res.headers()).or_else(|| match res.status() {
+        StatusCode::OK => None,
+        status => Some(http_code(status)),
+    })
+}
+
+pub fn status_code(headers: &HeaderMap) -> Option<tonic::Code> {
+    headers
+        .get(GRPC_STATUS)
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.parse::<i32>().ok())
+        .map(tonic::Code::from)
+}
+
+/*
+https://github.com/grpc/grpc/blob/master/doc/http-grpc-status-mapping.md
+ */
+fn http_code(status: StatusCode) -> tonic::Code {
+    match status {
+        StatusCode::BAD_REQUEST => tonic::Code::Internal,
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+        StatusCode::NOT_FOUND => tonic::Code::Unimplemented,
+        StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => tonic::Code::Unavailable,
+        _ => tonic::Code::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Response, StatusCode};
+
+    use super::response_code;
+
+    fn response(status: StatusCode, grpc_status: Option<&str>) -> Response<()> {
+        let mut res = Response::builder().status(status);
+
+        if let Some(grpc_status) = grpc_status {
+            res = res.header("grpc-status", grpc_status);
+        }
+
+        res.body(()).unwrap()
+    }
+
+    #[test]
+    fn prefers_grpc_status_header() {
+        assert_eq!(
+            response_code(&response(StatusCode::OK, Some("5"))),
+            Some(tonic::Code::NotFound)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_http_status() {
+        assert_eq!(
+            response_code(&response(StatusCode::SERVICE_UNAVAILABLE, None)),
+            Some(tonic::Code::Unavailable)
+        );
+        assert_eq!(
+            response_code(&response(StatusCode::INTERNAL_SERVER_ERROR, None)),
+            Some(tonic::Code::Unknown)
+        );
+    }
+
+    #[test]
+    fn waits_for_trailers_on_plain_ok() {
+        assert_eq!(response_code(&response(StatusCode::OK, None)), None);
+    }
+}
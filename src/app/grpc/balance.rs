@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::http::Uri;
+use tokio::{sync::mpsc::Sender, task::JoinSet};
+use tonic::transport::{Channel, Endpoint, channel::Change};
+use tonic_health::pb::{
+    HealthCheckRequest, health_check_response::ServingStatus, health_client::HealthClient,
+};
+use tracing::{info, warn};
+
+use bzd_lib::error::Error;
+
+use crate::app::{
+    error::AppError,
+    grpc::tls::Tls,
+    settings::{ClientSettings, DiscoverySettings},
+};
+
+const CAPACITY: usize = 64;
+
+pub type Endpoints = Arc<RwLock<Vec<String>>>;
+
+pub async fn channel(settings: &ClientSettings) -> Result<(Channel, Endpoints), Error> {
+    let tls = match &settings.tls {
+        Some(tls) => Some(Tls::load(tls).await?),
+        None => None,
+    };
+
+    let endpoints = settings
+        .endpoints
+        .iter()
+        .map(|it| endpoint(settings, it, tls.as_ref(), None))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    /*
+    Статический список без discovery тоже проверяем по health: иначе мёртвая реплика
+    так и останется в балансировщике. Единственный адрес выкидывать некуда
+     */
+    let discovery = match (&settings.discovery, <[_; 1]>::try_from(endpoints)) {
+        (Some(discovery), _) => discovery.clone(),
+        (None, Ok([endpoint])) => {
+            return Ok((
+                endpoint.connect_lazy(),
+                Arc::new(RwLock::new(settings.endpoints.clone())),
+            ));
+        }
+        (None, Err(_)) => DiscoverySettings {
+            interval: settings.health_check_interval,
+            resolve_dns: false,
+            health_check: true,
+        },
+    };
+
+    let (channel, tx) = Channel::balance_channel(CAPACITY);
+    let active = Endpoints::default();
+
+    tokio::spawn(discover(
+        settings.clone(),
+        discovery,
+        tls,
+        tx,
+        active.clone(),
+    ));
+
+    Ok((channel, active))
+}
+
+pub fn endpoint(
+    settings: &ClientSettings,
+    uri: &str,
+    tls: Option<&Tls>,
+    host: Option<&str>,
+) -> Result<Endpoint, AppError> {
+    let mut endpoint = Endpoint::new(uri.to_owned())?
+        .connect_timeout(settings.connect_timeout)
+        .timeout(settings.timeout)
+        .tcp_nodelay(settings.tcp_nodelay)
+        .tcp_keepalive(settings.tcp_keepalive);
+
+    if let Some(keepalive) = &settings.keepalive {
+        endpoint = endpoint
+            .http2_keep_alive_interval(keepalive.interval)
+            .keep_alive_timeout(keepalive.timeout)
+            .keep_alive_while_idle(keepalive.while_idle);
+    }
+
+    if let Some(tls) = tls {
+        endpoint = endpoint.tls_config(tls.config(host))?;
+    }
+
+    Ok(endpoint)
+}
+
+/*
+Раз в interval пересобираем список адресов (с DNS, если resolve_dns) и выкидываем
+из балансировщика те, что не отвечают SERVING по grpc.health.v1. Если здоровых нет
+совсем, оставляем все — пусть решают таймауты и circuit breaker
+ */
+async fn discover(
+    settings: ClientSettings,
+    discovery: DiscoverySettings,
+    tls: Option<Tls>,
+    tx: Sender<Change<String, Endpoint>>,
+    active: Endpoints,
+) {
+    let mut current = HashMap::<String, Option<String>>::new();
+    let mut probes = HashMap::<String, HealthClient<Channel>>::new();
+    let mut interval = tokio::time::interval(discovery.interval);
+
+    loop {
+        interval.tick().await;
+
+        let targets = resolve(&settings.endpoints, discovery.resolve_dns).await;
+
+        probes.retain(|it, _| targets.contains_key(it));
+
+        let healthy = if discovery.health_check {
+            check(&settings, tls.as_ref(), &targets, &mut probes).await
+        } else {
+            targets.clone()
+        };
+
+        let desired = if healthy.is_empty() { targets } else { healthy };
+
+        for (uri, host) in &desired {
+            if current.contains_key(uri) {
+                continue;
+            }
+
+            let Ok(endpoint) = endpoint(&settings, uri, tls.as_ref(), host.as_deref()) else {
+                continue;
+            };
+
+            info!("balance: {} added", uri);
+
+            if tx
+                .send(Change::Insert(uri.clone(), endpoint))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        for uri in current.keys().filter(|it| !desired.contains_key(*it)) {
+            warn!("balance: {} removed", uri);
+
+            if tx.send(Change::Remove(uri.clone())).await.is_err() {
+                return;
+            }
+        }
+
+        if let Ok(mut active) = active.write() {
+            *active = desired.keys().cloned().collect();
+        }
+
+        current = desired;
+    }
+}
+
+/*
+Адрес апстрима -> исходный хост, если адрес получен через DNS (нужен для SNI)
+ */
+async fn resolve(endpoints: &[String], resolve_dns: bool) -> HashMap<String, Option<String>> {
+    let mut targets = HashMap::new();
+
+    for endpoint in endpoints {
+        if !resolve_dns {
+            targets.insert(endpoint.clone(), None);
+            continue;
+        }
+
+        let Ok(uri) = endpoint.parse::<Uri>() else {
+            continue;
+        };
+
+        let scheme = uri.scheme_str().unwrap_or("http");
+        let port = uri
+            .port_u16()
+            .unwrap_or(if scheme == "https" { 443 } else { 80 });
+
+        let Some(host) = uri.host() else {
+            continue;
+        };
+
+        match tokio::net::lookup_host((host, port)).await {
+            Ok(addrs) => {
+                targets.extend(addrs.map(|it| (format!("{scheme}://{it}"), Some(host.to_owned()))))
+            }
+            Err(err) => warn!("balance: failed to resolve {}: {}", host, err),
+        }
+    }
+
+    targets
+}
+
+async fn check(
+    settings: &ClientSettings,
+    tls: Option<&Tls>,
+    targets: &HashMap<String, Option<String>>,
+    probes: &mut HashMap<String, HealthClient<Channel>>,
+) -> HashMap<String, Option<String>> {
+    let mut checks = JoinSet::new();
+
+    for (uri, host) in targets {
+        let probe = match probes.get(uri) {
+            Some(probe) => probe.clone(),
+            None => {
+                let Ok(endpoint) = endpoint(settings, uri, tls, host.as_deref()) else {
+                    continue;
+                };
+
+                probes
+                    .entry(uri.clone())
+                    .or_insert(HealthClient::new(endpoint.connect_lazy()))
+                    .clone()
+            }
+        };
+
+        checks.spawn(is_serving(
+            (uri.clone(), host.clone()),
+            probe,
+            settings.timeout,
+        ));
+    }
+
+    checks.join_all().await.into_iter().flatten().collect()
+}
+
+async fn is_serving(
+    target: (String, Option<String>),
+    mut probe: HealthClient<Channel>,
+    timeout: Duration,
+) -> Option<(String, Option<String>)> {
+    let req = HealthCheckRequest {
+        service: String::new(),
+    };
+
+    match tokio::time::timeout(timeout, probe.check(req)).await {
+        Ok(Ok(res)) if res.get_ref().status() == ServingStatus::Serving => Some(target),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{extract::Request, routing::any};
+    use tonic_health::pb::{
+        HealthCheckRequest, HealthCheckResponse, health_check_response::ServingStatus,
+    };
+
+    use super::channel;
+    use crate::app::grpc::testing::{client, reply, serve};
+
+    fn upstream(status: ServingStatus) -> String {
+        let router = axum::Router::new().fallback(any(move |req: Request| async move {
+            reply::<HealthCheckRequest, _>(
+                req,
+                HealthCheckResponse {
+                    status: status as i32,
+                },
+            )
+            .await
+        }));
+
+        format!("http://{}", serve(router, None))
+    }
+
+    #[tokio::test]
+    async fn static_list_drops_unhealthy_endpoint() {
+        let healthy = upstream(ServingStatus::Serving);
+        let unhealthy = upstream(ServingStatus::NotServing);
+
+        let mut settings = client(&[healthy.clone(), unhealthy]);
+        settings.health_check_interval = Duration::from_millis(50);
+
+        let (_channel, active) = channel(&settings).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(*active.read().unwrap(), [healthy]);
+    }
+}
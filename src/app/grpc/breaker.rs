@@ -0,0 +1,418 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::http::{Request, Response};
+use tonic::body::Body;
+use tonic_types::{ErrorDetails, StatusExt as _};
+use tower::{BoxError, Layer, Service};
+use tracing::warn;
+
+use crate::app::{grpc, metrics, settings::BreakerSettings};
+
+pub struct CircuitBreaker {
+    name: &'static str,
+    settings: Option<BreakerSettings>,
+    state: Mutex<State>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probes: u32 },
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, settings: Option<BreakerSettings>) -> Arc<Self> {
+        metrics::set_circuit_state(name, 0);
+
+        Arc::new(Self {
+            name,
+            settings,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        })
+    }
+
+    pub fn state(&self) -> &'static str {
+        match self.state.lock().map(|it| *it) {
+            Ok(State::Closed { .. }) | Err(_) => "closed",
+            Ok(State::Open { .. }) => "open",
+            Ok(State::HalfOpen { .. }) => "half_open",
+        }
+    }
+
+    /*
+    После open_duration пропускаем до half_open_max_calls пробных вызовов:
+    первый успешный закрывает цепь, первый неуспешный снова открывает
+     */
+    fn acquire(self: &Arc<Self>) -> Result<Permit, Duration> {
+        let mut permit = Permit {
+            breaker: self.clone(),
+            probe: false,
+        };
+
+        let Some(settings) = &self.settings else {
+            return Ok(permit);
+        };
+
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(permit);
+        };
+
+        let now = Instant::now();
+
+        match *state {
+            State::Closed { .. } => Ok(permit),
+            State::Open { until } if now < until => Err(until - now),
+            State::Open { .. } => {
+                self.transition(&mut state, State::HalfOpen { probes: 1 });
+                permit.probe = true;
+
+                Ok(permit)
+            }
+            State::HalfOpen { probes } if probes < settings.half_open_max_calls => {
+                *state = State::HalfOpen { probes: probes + 1 };
+                permit.probe = true;
+
+                Ok(permit)
+            }
+            State::HalfOpen { .. } => Err(settings.open_duration),
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let Some(settings) = &self.settings else {
+            return;
+        };
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let open = State::Open {
+            until: Instant::now() + settings.open_duration,
+        };
+
+        match (*state, success) {
+            (State::Closed { .. }, true) => *state = State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 >= settings.failure_threshold => {
+                self.transition(&mut state, open)
+            }
+            (State::Closed { failures }, false) => {
+                *state = State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (State::HalfOpen { .. }, true) => {
+                self.transition(&mut state, State::Closed { failures: 0 })
+            }
+            (State::HalfOpen { .. }, false) => self.transition(&mut state, open),
+            (State::Open { .. }, _) => {}
+        }
+    }
+
+    /*
+    Пробный вызов, который так и не завершился (таймаут снаружи, отвалился клиент),
+    считаем неуспешным, иначе цепь навсегда застрянет в half-open без свободных слотов
+     */
+    fn abandon(&self) {
+        let Some(settings) = &self.settings else {
+            return;
+        };
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        if let State::HalfOpen { .. } = *state {
+            let open = State::Open {
+                until: Instant::now() + settings.open_duration,
+            };
+
+            self.transition(&mut state, open);
+        }
+    }
+
+    fn transition(&self, state: &mut State, next: State) {
+        let value = match next {
+            State::Closed { .. } => 0,
+            State::HalfOpen { .. } => 1,
+            State::Open { .. } => 2,
+        };
+
+        if matches!(next, State::Open { .. }) {
+            warn!("breaker: {} circuit opened", self.name);
+        }
+
+        metrics::set_circuit_state(self.name, value);
+
+        *state = next;
+    }
+}
+
+struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    probe: bool,
+}
+
+impl Permit {
+    fn record(mut self, success: bool) {
+        self.probe = false;
+        self.breaker.record(success);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.abandon();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BreakerLayer {
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl BreakerLayer {
+    pub fn new(breaker: Arc<CircuitBreaker>) -> Self {
+        Self { breaker }
+    }
+}
+
+impl<S> Layer<S> for BreakerLayer {
+    type Service = Breaker<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Breaker {
+            inner,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Breaker<S> {
+    inner: S,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<S> Service<Request<Body>> for Breaker<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+        let breaker = self.breaker.clone();
+
+        Box::pin(async move {
+            let permit = match breaker.acquire() {
+                Ok(permit) => permit,
+                Err(retry_after) => {
+                    return Err(tonic::Status::with_error_details(
+                        tonic::Code::Unavailable,
+                        format!("{}: circuit open", breaker.name),
+                        ErrorDetails::with_retry_info(Some(retry_after)),
+                    )
+                    .into());
+                }
+            };
+
+            let res = inner.call(req).await.map_err(Into::into);
+
+            permit.record(match &res {
+                Ok(res) => !is_failure(res),
+                Err(_) => false,
+            });
+
+            res
+        })
+    }
+}
+
+/*
+Бизнесовые ошибки (NotFound, InvalidArgument и т.п.) говорят о том, что апстрим жив,
+поэтому цепь размыкают только транспортные ошибки, Unavailable и DeadlineExceeded
+ */
+fn is_failure<B>(res: &Response<B>) -> bool {
+    matches!(
+        grpc::response_code(res),
+        Some(tonic::Code::Unavailable | tonic::Code::DeadlineExceeded)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use axum::http::{Request, Response};
+    use tonic::body::Body;
+    use tower::{BoxError, Layer as _, Service, ServiceExt as _, service_fn};
+
+    use super::{BreakerLayer, CircuitBreaker};
+    use crate::app::settings::BreakerSettings;
+
+    const OPEN_DURATION: Duration = Duration::from_millis(50);
+
+    #[derive(Clone, Copy)]
+    enum Upstream {
+        Ok,
+        Unavailable,
+        Hang,
+    }
+
+    /*
+    Фейковый апстрим: отвечает grpc-status в trailers-only заголовках
+    или не отвечает вовсе
+     */
+    fn upstream(
+        mode: Arc<Mutex<Upstream>>,
+    ) -> impl Service<Request<Body>, Response = Response<Body>, Error = BoxError, Future: Send>
+    + Clone
+    + Send
+    + 'static {
+        service_fn(move |_: Request<Body>| {
+            let mode = *mode.lock().unwrap();
+
+            async move {
+                let code = match mode {
+                    Upstream::Ok => tonic::Code::Ok,
+                    Upstream::Unavailable => tonic::Code::Unavailable,
+                    Upstream::Hang => std::future::pending().await,
+                };
+
+                Ok(Response::builder()
+                    .header("grpc-status", (code as i32).to_string())
+                    .body(Body::empty())
+                    .unwrap())
+            }
+        })
+    }
+
+    fn breaker() -> (Arc<CircuitBreaker>, Arc<Mutex<Upstream>>) {
+        let breaker = CircuitBreaker::new(
+            "test",
+            Some(BreakerSettings {
+                failure_threshold: 2,
+                open_duration: OPEN_DURATION,
+                half_open_max_calls: 1,
+            }),
+        );
+
+        (breaker, Arc::new(Mutex::new(Upstream::Ok)))
+    }
+
+    async fn call(
+        breaker: &Arc<CircuitBreaker>,
+        mode: &Arc<Mutex<Upstream>>,
+    ) -> Result<Response<Body>, BoxError> {
+        BreakerLayer::new(breaker.clone())
+            .layer(upstream(mode.clone()))
+            .oneshot(Request::new(Body::empty()))
+            .await
+    }
+
+    async fn open(breaker: &Arc<CircuitBreaker>, mode: &Arc<Mutex<Upstream>>) {
+        *mode.lock().unwrap() = Upstream::Unavailable;
+
+        call(breaker, mode).await.unwrap();
+        assert_eq!(breaker.state(), "closed");
+
+        call(breaker, mode).await.unwrap();
+        assert_eq!(breaker.state(), "open");
+
+        let err = call(breaker, mode).await.unwrap_err();
+        let status = err.downcast::<tonic::Status>().unwrap();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn closes_after_successful_probe() {
+        let (breaker, mode) = breaker();
+
+        open(&breaker, &mode).await;
+
+        tokio::time::sleep(OPEN_DURATION).await;
+        *mode.lock().unwrap() = Upstream::Ok;
+
+        call(&breaker, &mode).await.unwrap();
+        assert_eq!(breaker.state(), "closed");
+
+        call(&breaker, &mode).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopens_after_failed_probe() {
+        let (breaker, mode) = breaker();
+
+        open(&breaker, &mode).await;
+
+        tokio::time::sleep(OPEN_DURATION).await;
+
+        call(&breaker, &mode).await.unwrap();
+        assert_eq!(breaker.state(), "open");
+
+        assert!(call(&breaker, &mode).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn limits_concurrent_probes() {
+        let (breaker, mode) = breaker();
+
+        open(&breaker, &mode).await;
+
+        tokio::time::sleep(OPEN_DURATION).await;
+        *mode.lock().unwrap() = Upstream::Hang;
+
+        let probe = tokio::spawn({
+            let (breaker, mode) = (breaker.clone(), mode.clone());
+
+            async move { call(&breaker, &mode).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(breaker.state(), "half_open");
+        assert!(call(&breaker, &mode).await.is_err());
+
+        probe.abort();
+    }
+
+    #[tokio::test]
+    async fn recovers_after_dropped_probe() {
+        let (breaker, mode) = breaker();
+
+        open(&breaker, &mode).await;
+
+        tokio::time::sleep(OPEN_DURATION).await;
+        *mode.lock().unwrap() = Upstream::Hang;
+
+        let probe = tokio::time::timeout(Duration::from_millis(10), call(&breaker, &mode)).await;
+        assert!(probe.is_err());
+        assert_eq!(breaker.state(), "open");
+
+        tokio::time::sleep(OPEN_DURATION).await;
+        *mode.lock().unwrap() = Upstream::Ok;
+
+        call(&breaker, &mode).await.unwrap();
+        assert_eq!(breaker.state(), "closed");
+    }
+}
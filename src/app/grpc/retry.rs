@@ -0,0 +1,379 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::http::{HeaderValue, Request, Response};
+use http_body_util::{BodyExt as _, Full};
+use tonic::body::Body;
+use tower::{
+    BoxError, Layer, Service, ServiceExt as _,
+    retry::budget::{Budget as _, TpsBudget},
+};
+use tracing::debug;
+
+use crate::app::{
+    deadline, grpc, idempotency_key,
+    settings::{ClientSettings, RetrySettings},
+};
+
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/*
+Ретраим только чтения: повтор create_message без ключа идемпотентности
+может создать дубль
+ */
+const READ_ONLY_METHODS: &[&str] = &[
+    "GetUser",
+    "GetUsers",
+    "GetSources",
+    "GetTopics",
+    "GetTopic",
+    "GetTopicsUsers",
+];
+
+#[derive(Clone)]
+pub struct RetryLayer {
+    settings: Option<RetrySettings>,
+    timeout: Duration,
+    budget: Arc<TpsBudget>,
+}
+
+impl RetryLayer {
+    pub fn new(settings: &ClientSettings) -> Self {
+        let budget = match &settings.retry {
+            Some(settings) => TpsBudget::new(
+                settings.budget_ttl,
+                settings.budget_min_per_sec,
+                settings.budget_percent,
+            ),
+            None => TpsBudget::default(),
+        };
+
+        Self {
+            settings: settings.retry.clone(),
+            timeout: settings.timeout,
+            budget: Arc::new(budget),
+        }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = Retry<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Retry {
+            inner,
+            settings: self.settings.clone(),
+            timeout: self.timeout,
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Retry<S> {
+    inner: S,
+    settings: Option<RetrySettings>,
+    timeout: Duration,
+    budget: Arc<TpsBudget>,
+}
+
+impl<S> Service<Request<Body>> for Retry<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+
+        let Some(settings) = self.settings.clone().filter(|_| is_retryable(&req)) else {
+            return Box::pin(async move { inner.call(req).await.map_err(Into::into) });
+        };
+
+        let timeout = self.timeout;
+        let budget = self.budget.clone();
+
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = body.collect().await?.to_bytes();
+
+            budget.deposit();
+
+            let mut attempt = 0;
+
+            loop {
+                let mut req =
+                    Request::from_parts(parts.clone(), Body::new(Full::new(body.clone())));
+
+                if let Some(remaining) = deadline::remaining() {
+                    set_timeout(&mut req, remaining.min(timeout));
+                }
+
+                /*
+                Канал tonic — это Buffer: каждый вызов должен занимать слот через poll_ready,
+                а после первой попытки у нас на руках свежий клон без слота
+                 */
+                let res = match inner.ready().await.map_err(Into::into) {
+                    Ok(inner) => inner.call(req).await.map_err(Into::into),
+                    Err(err) => Err(err),
+                };
+
+                attempt += 1;
+
+                let should_retry = match &res {
+                    Ok(res) => is_unavailable(res),
+                    Err(err) => is_connect_error(err.as_ref()),
+                };
+
+                if !should_retry || attempt >= settings.max_attempts {
+                    return res;
+                }
+
+                let backoff = backoff(&settings, attempt);
+
+                if deadline::remaining().is_some_and(|it| it <= backoff) || !budget.withdraw() {
+                    return res;
+                }
+
+                debug!(
+                    "retry: {} attempt {} failed, retrying in {:?}",
+                    parts.uri.path(),
+                    attempt,
+                    backoff
+                );
+
+                tokio::time::sleep(backoff).await;
+            }
+        })
+    }
+}
+
+fn is_retryable(req: &Request<Body>) -> bool {
+    let method = req.uri().path().rsplit('/').next().unwrap_or_default();
+
+    READ_ONLY_METHODS.contains(&method) || req.headers().contains_key(idempotency_key::HEADER)
+}
+
+fn is_unavailable<B>(res: &Response<B>) -> bool {
+    grpc::response_code(res) == Some(tonic::Code::Unavailable)
+}
+
+/*
+Повторяем только неудачное подключение: запрос до апстрима не дошёл. Таймаут
+(TimeoutExpired) значит, что апстрим и так тормозит — повтор лишь добавит нагрузки
+ */
+fn is_connect_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+
+    while let Some(err) = source {
+        if err.is::<tonic::ConnectError>() {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
+
+/*
+Full jitter: случайная пауза от нуля до base * 2^attempt, но не больше max_backoff
+ */
+fn backoff(settings: &RetrySettings, attempt: u32) -> Duration {
+    let ceiling = settings
+        .base_backoff
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(settings.max_backoff);
+
+    ceiling.mul_f64(rand::random::<f64>())
+}
+
+/*
+grpc-timeout проставлен интерсептором до первой попытки, для повторов
+урезаем его до остатка дедлайна
+ */
+fn set_timeout(req: &mut Request<Body>, timeout: Duration) {
+    if let Ok(value) = HeaderValue::from_str(&format!("{}m", timeout.as_millis())) {
+        req.headers_mut().insert(GRPC_TIMEOUT, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use axum::http::{HeaderValue, Request, Response};
+    use tonic::body::Body;
+    use tower::{BoxError, Layer as _, ServiceExt as _, buffer::Buffer, service_fn};
+
+    use super::RetryLayer;
+    use crate::app::{
+        deadline,
+        grpc::testing::client,
+        idempotency_key,
+        settings::{ClientSettings, RetrySettings},
+    };
+
+    const READ: &str = "/bzd.users.UsersService/GetUser";
+    const WRITE: &str = "/bzd.messages.MessagesService/CreateMessage";
+
+    fn settings() -> ClientSettings {
+        ClientSettings {
+            retry: Some(RetrySettings {
+                max_attempts: 3,
+                base_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(5),
+                budget_ttl: Duration::from_secs(10),
+                budget_min_per_sec: 10,
+                budget_percent: 0.2,
+            }),
+            ..client(&[])
+        }
+    }
+
+    fn respond(code: tonic::Code) -> Response<Body> {
+        let mut res = Response::new(Body::empty());
+        res.headers_mut()
+            .insert("grpc-status", HeaderValue::from(code as i32));
+
+        res
+    }
+
+    /*
+    Апстрим за Buffer, как настоящий канал tonic: каждый вызов без poll_ready паникует.
+    Первые failures вызовов отвечают результатом fail, дальше — Ok
+     */
+    async fn call(
+        settings: &ClientSettings,
+        req: Request<Body>,
+        failures: usize,
+        fail: fn() -> Result<Response<Body>, BoxError>,
+    ) -> (Result<Response<Body>, BoxError>, usize) {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let inner = Buffer::new(
+            service_fn({
+                let calls = calls.clone();
+
+                move |_: Request<Body>| {
+                    let call = calls.fetch_add(1, Ordering::SeqCst);
+
+                    async move {
+                        if call < failures {
+                            fail()
+                        } else {
+                            Ok(respond(tonic::Code::Ok))
+                        }
+                    }
+                }
+            }),
+            8,
+        );
+
+        let res = RetryLayer::new(settings).layer(inner).oneshot(req).await;
+
+        (res, calls.load(Ordering::SeqCst))
+    }
+
+    fn request(path: &str) -> Request<Body> {
+        Request::post(path).body(Body::empty()).unwrap()
+    }
+
+    fn unavailable() -> Result<Response<Body>, BoxError> {
+        Ok(respond(tonic::Code::Unavailable))
+    }
+
+    #[tokio::test]
+    async fn retries_unavailable_read() {
+        let (res, calls) = call(&settings(), request(READ), 2, unavailable).await;
+
+        assert_eq!(res.unwrap().headers()["grpc-status"], "0");
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (res, calls) = call(&settings(), request(READ), 5, unavailable).await;
+
+        assert_eq!(res.unwrap().headers()["grpc-status"], "14");
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn retries_write_only_with_idempotency_key() {
+        let (_, calls) = call(&settings(), request(WRITE), 1, unavailable).await;
+
+        assert_eq!(calls, 1);
+
+        let mut req = request(WRITE);
+        req.headers_mut()
+            .insert(idempotency_key::HEADER, HeaderValue::from_static("key"));
+
+        let (_, calls) = call(&settings(), req, 1, unavailable).await;
+
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn stops_when_budget_is_exhausted() {
+        let mut settings = settings();
+
+        if let Some(retry) = &mut settings.retry {
+            retry.budget_min_per_sec = 0;
+            retry.budget_percent = 0.0;
+        }
+
+        let (_, calls) = call(&settings, request(READ), 1, unavailable).await;
+
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn stops_when_deadline_would_pass() {
+        let (_, calls) = deadline::scope(
+            Duration::ZERO,
+            call(&settings(), request(READ), 1, unavailable),
+        )
+        .await;
+
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn retries_connect_error_but_not_timeout() {
+        let (res, calls) = call(&settings(), request(READ), 1, || {
+            Err(tonic::ConnectError("connection refused".into()).into())
+        })
+        .await;
+
+        assert!(res.is_ok());
+        assert_eq!(calls, 2);
+
+        let (res, calls) = call(&settings(), request(READ), 1, || {
+            Err(tonic::TimeoutExpired(()).into())
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+}
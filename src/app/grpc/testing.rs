@@ -0,0 +1,71 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{extract::Request, http::Response};
+use tonic::{
+    body::Body,
+    server::{Grpc, UnaryService},
+    service::Routes,
+    transport::{Server, ServerTlsConfig, server::TcpIncoming},
+};
+use tonic_prost::ProstCodec;
+
+use crate::app::settings::ClientSettings;
+
+/*
+Моки апстримов для тестов: axum-роутер, поднятый как gRPC-сервер на случайном порту
+ */
+pub fn serve(router: axum::Router, tls: Option<ServerTlsConfig>) -> SocketAddr {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = incoming.local_addr().unwrap();
+
+    let mut server = Server::builder();
+
+    if let Some(tls) = tls {
+        server = server.tls_config(tls).unwrap();
+    }
+
+    tokio::spawn(
+        server
+            .add_routes(Routes::from(router))
+            .serve_with_incoming(incoming),
+    );
+
+    addr
+}
+
+pub async fn reply<Req, T>(req: Request, res: T) -> Response<Body>
+where
+    Req: prost::Message + Default + Send + 'static,
+    T: prost::Message + Clone + Send + 'static,
+{
+    Grpc::new(ProstCodec::<T, Req>::default())
+        .unary(Reply(res), req)
+        .await
+}
+
+struct Reply<T>(T);
+
+impl<Req, T: Clone + Send + 'static> UnaryService<Req> for Reply<T> {
+    type Response = T;
+    type Future = std::future::Ready<Result<tonic::Response<T>, tonic::Status>>;
+
+    fn call(&mut self, _: tonic::Request<Req>) -> Self::Future {
+        std::future::ready(Ok(tonic::Response::new(self.0.clone())))
+    }
+}
+
+pub fn client(endpoints: &[String]) -> ClientSettings {
+    ClientSettings {
+        endpoints: endpoints.to_vec(),
+        discovery: None,
+        health_check_interval: Duration::from_secs(10),
+        connect_timeout: Duration::from_secs(1),
+        timeout: Duration::from_secs(5),
+        tcp_keepalive: None,
+        tcp_nodelay: true,
+        keepalive: None,
+        retry: None,
+        breaker: None,
+        tls: None,
+    }
+}
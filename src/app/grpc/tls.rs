@@ -0,0 +1,206 @@
+use bzd_lib::error::Error;
+use config::ConfigError;
+use tokio::fs;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
+use crate::app::settings::TlsSettings;
+
+#[derive(Clone)]
+pub struct Tls {
+    config: ClientTlsConfig,
+    domain_name: Option<String>,
+}
+
+impl Tls {
+    pub async fn load(settings: &TlsSettings) -> Result<Self, Error> {
+        let mut config = ClientTlsConfig::new();
+
+        config = match &settings.ca_file {
+            Some(ca_file) => config.ca_certificate(Certificate::from_pem(fs::read(ca_file).await?)),
+            None => config.with_enabled_roots(),
+        };
+
+        match (&settings.cert_file, &settings.key_file) {
+            (Some(cert_file), Some(key_file)) => {
+                config = config.identity(Identity::from_pem(
+                    fs::read(cert_file).await?,
+                    fs::read(key_file).await?,
+                ));
+            }
+            (Some(_), None) => return Err(missing("key_file", "cert_file").into()),
+            (None, Some(_)) => return Err(missing("cert_file", "key_file").into()),
+            (None, None) => {}
+        }
+
+        Ok(Self {
+            config,
+            domain_name: settings.domain_name.clone(),
+        })
+    }
+
+    /*
+    При resolve_dns в URI эндпоинта уже IP, поэтому без явного domain_name
+    проверяем сертификат по исходному хосту из настроек
+     */
+    pub fn config(&self, host: Option<&str>) -> ClientTlsConfig {
+        match self.domain_name.as_deref().or(host) {
+            Some(domain_name) => self.config.clone().domain_name(domain_name),
+            None => self.config.clone(),
+        }
+    }
+}
+
+fn missing(field: &str, pair: &str) -> ConfigError {
+    ConfigError::Message(format!("tls: {field} is required when {pair} is set"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use axum::{extract::Request, routing::any};
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+    use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+    use tonic_health::pb::{
+        HealthCheckRequest, HealthCheckResponse, health_check_response::ServingStatus,
+        health_client::HealthClient,
+    };
+
+    use super::Tls;
+    use crate::app::{
+        grpc::{
+            balance,
+            testing::{client, reply, serve},
+        },
+        settings::TlsSettings,
+    };
+
+    const DOMAIN_NAME: &str = "bzd-users.test";
+
+    struct Pki {
+        dir: PathBuf,
+        ca: String,
+        server: (String, String),
+    }
+
+    /*
+    Свой CA на каждый тест: им подписаны сертификат сервера на DOMAIN_NAME
+    и клиентский сертификат гейтвея
+     */
+    fn pki() -> Pki {
+        let dir = std::env::temp_dir().join(format!("bzd-gw-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut params = CertificateParams::new(vec![]).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.into()])
+                .unwrap()
+                .signed_by(&key, &ca)
+                .unwrap();
+
+            (cert.pem(), key.serialize_pem())
+        };
+
+        let server = issue(DOMAIN_NAME);
+        let (cert, key) = issue("bzd-gw");
+
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert).unwrap();
+        std::fs::write(dir.join("key.pem"), key).unwrap();
+
+        Pki {
+            ca: ca.pem(),
+            dir,
+            server,
+        }
+    }
+
+    impl Pki {
+        fn path(&self, name: &str) -> Option<String> {
+            Some(self.dir.join(name).to_string_lossy().into_owned())
+        }
+
+        fn settings(&self, domain_name: Option<&str>, identity: bool) -> TlsSettings {
+            TlsSettings {
+                ca_file: self.path("ca.pem"),
+                cert_file: identity.then(|| self.path("cert.pem")).flatten(),
+                key_file: identity.then(|| self.path("key.pem")).flatten(),
+                domain_name: domain_name.map(Into::into),
+            }
+        }
+
+        fn server(&self) -> String {
+            let router = axum::Router::new().fallback(any(|req: Request| {
+                reply::<HealthCheckRequest, _>(
+                    req,
+                    HealthCheckResponse {
+                        status: ServingStatus::Serving.into(),
+                    },
+                )
+            }));
+
+            let tls = ServerTlsConfig::new()
+                .identity(Identity::from_pem(&self.server.0, &self.server.1))
+                .client_ca_root(Certificate::from_pem(&self.ca));
+
+            format!("https://{}", serve(router, Some(tls)))
+        }
+    }
+
+    async fn check(pki: &Pki, settings: TlsSettings) -> Result<(), tonic::Status> {
+        let mut client = client(&[pki.server()]);
+        client.tls = Some(settings);
+
+        let (channel, _) = balance::channel(&client).await.unwrap();
+
+        HealthClient::new(channel)
+            .check(HealthCheckRequest::default())
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn connects_with_mtls_and_domain_name_override() {
+        let pki = pki();
+
+        check(&pki, pki.settings(Some(DOMAIN_NAME), true))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_certificate_for_another_host() {
+        let pki = pki();
+
+        assert!(check(&pki, pki.settings(None, true)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejected_without_client_identity() {
+        let pki = pki();
+
+        assert!(
+            check(&pki, pki.settings(Some(DOMAIN_NAME), false))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn names_missing_key_file() {
+        let pki = pki();
+
+        let mut settings = pki.settings(None, true);
+        settings.key_file = None;
+
+        let Err(err) = Tls::load(&settings).await else {
+            panic!("loaded identity without key_file");
+        };
+
+        assert!(format!("{err:?}").contains("key_file"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::{Arc, RwLock};
+
+use bzd_lib::error::Error;
+use config::ConfigError;
+use tonic::{service::interceptor::InterceptedService, transport::Channel};
+use tonic_health::pb::health_client::HealthClient;
+use tower::Layer as _;
+
+use crate::app::{
+    grpc::{
+        Interceptor, ServiceChannel, balance,
+        breaker::{BreakerLayer, CircuitBreaker},
+        retry::RetryLayer,
+    },
+    settings::{ClientSettings, ClientsSettings},
+    telemetry::grpc::{Trace, TraceLayer},
+};
+
+/*
+Все клиенты одного бэкенда ходят через общий канал: одно HTTP/2-соединение
+на адрес, общие keepalive, бюджет ретраев и circuit breaker
+ */
+pub struct Upstreams {
+    pub bzd_users: Upstream,
+    pub bzd_messages: Upstream,
+}
+
+pub struct Upstream {
+    pub name: &'static str,
+    pub channel: ServiceChannel,
+    /*
+    Проба готовности идёт мимо ретраев и breaker: она должна видеть сам апстрим,
+    а не состояние breaker, и не расходовать его счётчики
+     */
+    pub health_client: HealthClient<Trace<Channel>>,
+    pub breaker: Arc<CircuitBreaker>,
+    settings: ClientSettings,
+    endpoints: Arc<RwLock<Vec<String>>>,
+}
+
+impl Upstreams {
+    pub async fn new(settings: &ClientsSettings) -> Result<Arc<Self>, Error> {
+        Ok(Arc::new(Self {
+            bzd_users: Upstream::new("bzd_users", &settings.bzd_users).await?,
+            bzd_messages: Upstream::new("bzd_messages", &settings.bzd_messages).await?,
+        }))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Upstream> {
+        [&self.bzd_users, &self.bzd_messages].into_iter()
+    }
+}
+
+impl Upstream {
+    async fn new(name: &'static str, settings: &ClientSettings) -> Result<Self, Error> {
+        if settings.endpoints.is_empty() {
+            return Err(ConfigError::Message(format!(
+                "clients.{name}: endpoint or endpoints is required"
+            ))
+            .into());
+        }
+
+        let breaker = CircuitBreaker::new(name, settings.breaker.clone());
+        let (ch, endpoints) = balance::channel(settings).await?;
+
+        let health_client = HealthClient::new(TraceLayer.layer(ch.clone()));
+
+        let channel = InterceptedService::new(
+            BreakerLayer::new(breaker.clone())
+                .layer(RetryLayer::new(settings).layer(TraceLayer.layer(ch))),
+            Interceptor::new(settings.timeout),
+        );
+
+        Ok(Self {
+            name,
+            health_client,
+            channel,
+            breaker,
+            settings: settings.clone(),
+            endpoints,
+        })
+    }
+
+    pub fn endpoints(&self) -> Vec<String> {
+        self.endpoints
+            .read()
+            .map(|it| it.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn is_discovery(&self) -> bool {
+        self.settings.discovery.is_some()
+    }
+
+    pub fn is_tls(&self) -> bool {
+        self.settings.tls.is_some()
+    }
+}
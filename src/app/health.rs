@@ -0,0 +1,143 @@
+pub mod settings;
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use tonic_health::pb::{HealthCheckRequest, health_check_response::ServingStatus};
+
+use crate::app::{grpc::upstream::Upstream, json::AppJson, state::AppState};
+
+/*
+Liveness ничего не проверяет: ответил — значит процесс жив
+ */
+pub async fn livez() -> AppJson<livez::Response> {
+    AppJson(livez::Response {
+        status: livez::Status::Ok,
+    })
+}
+
+pub mod livez {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    pub struct Response {
+        pub status: Status,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Status {
+        Ok,
+    }
+}
+
+/*
+Каналы к апстримам ленивые, так что без явного grpc.health.v1 мы бы
+считали себя готовыми, даже если bzd-users или bzd-messages недоступны
+ */
+pub async fn readyz(
+    State(AppState {
+        settings,
+        decoding_keys,
+        upstreams,
+        shutdown,
+        ..
+    }): State<AppState>,
+) -> impl IntoResponse {
+    let timeout = settings.health.timeout;
+
+    let (bzd_users, bzd_messages) = tokio::join!(
+        check_upstream(&upstreams.bzd_users, timeout),
+        check_upstream(&upstreams.bzd_messages, timeout),
+    );
+
+    let decoding_keys = if decoding_keys.is_loaded() {
+        readyz::Check::ok("decoding_keys")
+    } else {
+        readyz::Check::fail("decoding_keys", "no decoding key loaded".into())
+    };
+
+    let shutdown = if shutdown.is_started() {
+        readyz::Check::fail("shutdown", "shutting down".into())
+    } else {
+        readyz::Check::ok("shutdown")
+    };
+
+    let checks = vec![bzd_users, bzd_messages, decoding_keys, shutdown];
+
+    let (code, status) = if checks.iter().all(|it| it.status == readyz::Status::Ok) {
+        (StatusCode::OK, readyz::Status::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, readyz::Status::Fail)
+    };
+
+    (code, AppJson(readyz::Response { status, checks }))
+}
+
+async fn check_upstream(upstream: &Upstream, timeout: Duration) -> readyz::Check {
+    let name = upstream.name;
+    let mut client = upstream.health_client.clone();
+    let req = HealthCheckRequest {
+        service: String::new(),
+    };
+
+    let check = match tokio::time::timeout(timeout, client.check(req)).await {
+        Ok(Ok(res)) if res.get_ref().status() == ServingStatus::Serving => readyz::Check::ok(name),
+        Ok(Ok(res)) => readyz::Check::fail(name, res.get_ref().status().as_str_name().into()),
+        Ok(Err(status)) => readyz::Check::fail(name, status.message().into()),
+        Err(_) => readyz::Check::fail(name, "timeout".into()),
+    };
+
+    readyz::Check {
+        circuit: Some(upstream.breaker.state()),
+        ..check
+    }
+}
+
+pub mod readyz {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    pub struct Response {
+        pub status: Status,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub checks: Vec<Check>,
+    }
+
+    #[derive(Serialize)]
+    pub struct Check {
+        pub name: &'static str,
+        pub status: Status,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub circuit: Option<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub error: Option<String>,
+    }
+
+    #[derive(Serialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Status {
+        Ok,
+        Fail,
+    }
+
+    impl Check {
+        pub fn ok(name: &'static str) -> Self {
+            Self {
+                name,
+                status: Status::Ok,
+                circuit: None,
+                error: None,
+            }
+        }
+
+        pub fn fail(name: &'static str, error: String) -> Self {
+            Self {
+                name,
+                status: Status::Fail,
+                circuit: None,
+                error: Some(error),
+            }
+        }
+    }
+}
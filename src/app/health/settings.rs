@@ -0,0 +1,9 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct HealthSettings {
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+}
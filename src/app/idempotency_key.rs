@@ -0,0 +1,24 @@
+use axum::{extract::Request, middleware::Next, response::Response};
+
+pub const HEADER: &str = "idempotency-key";
+
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static IDEMPOTENCY_KEY: Option<String>;
+}
+
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let idempotency_key = req
+        .headers()
+        .get(HEADER)
+        .and_then(|it| it.to_str().ok())
+        .filter(|it| !it.is_empty() && it.len() <= MAX_LEN)
+        .map(Into::into);
+
+    IDEMPOTENCY_KEY.scope(idempotency_key, next.run(req)).await
+}
+
+pub fn current() -> Option<String> {
+    IDEMPOTENCY_KEY.try_with(Clone::clone).ok().flatten()
+}
@@ -0,0 +1,219 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use bzd_lib::error::Error;
+use prometheus::{
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder, register_histogram_vec,
+    register_int_counter_vec, register_int_gauge_vec,
+};
+
+use crate::app::error::AppError;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+struct Metrics {
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    grpc_client_duration: HistogramVec,
+    app_errors: IntCounterVec,
+    circuit_state: IntGaugeVec,
+    key_reloads: IntCounterVec,
+}
+
+/*
+Регистрируем всё при старте: конфликт в реестре должен ронять запуск, а не первый запрос.
+До init (в тестах) наблюдения просто не пишутся
+ */
+pub fn init() -> Result<(), Error> {
+    let metrics = Metrics {
+        http_requests: register_int_counter_vec!(
+            "http_requests_total",
+            "HTTP requests handled by the gateway",
+            &["method", "route", "status"]
+        )?,
+        http_request_duration: register_histogram_vec!(
+            "http_request_duration_seconds",
+            "HTTP request latency",
+            &["method", "route", "status"]
+        )?,
+        grpc_client_duration: register_histogram_vec!(
+            "grpc_client_duration_seconds",
+            "Upstream gRPC call latency",
+            &["rpc", "code"]
+        )?,
+        app_errors: register_int_counter_vec!(
+            "app_errors_total",
+            "Errors returned by handlers, by AppError variant",
+            &["variant"]
+        )?,
+        circuit_state: register_int_gauge_vec!(
+            "circuit_breaker_state",
+            "Upstream circuit breaker state: 0 closed, 1 half-open, 2 open",
+            &["upstream"]
+        )?,
+        key_reloads: register_int_counter_vec!(
+            "key_reloads_total",
+            "Decoding key reloads from the public key file or JWKS",
+            &["source", "result"]
+        )?,
+    };
+
+    METRICS.set(metrics).map_err(|_| AppError::Internal)?;
+
+    Ok(())
+}
+
+/*
+Layer роутера выполняется уже после роутинга, поэтому MatchedPath лежит в запросе —
+и ответы, отданные до хендлера (429 от rate limiter), тоже получают свой маршрут.
+Сырые пути не используем, чтобы не раздувать кардинальность
+ */
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let started_at = Instant::now();
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|it| it.as_str().to_owned());
+
+    let res = next.run(req).await;
+
+    let Some(metrics) = METRICS.get() else {
+        return res;
+    };
+
+    let status = res.status();
+    let labels = [
+        method.as_str(),
+        route.as_deref().unwrap_or("unmatched"),
+        status.as_str(),
+    ];
+
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started_at.elapsed().as_secs_f64());
+
+    res
+}
+
+pub fn observe_grpc(service: &str, method: &str, code: &str, started_at: Instant) {
+    let Some(metrics) = METRICS.get() else {
+        return;
+    };
+
+    metrics
+        .grpc_client_duration
+        .with_label_values(&[rpc_name(service, method).as_str(), code])
+        .observe(started_at.elapsed().as_secs_f64());
+}
+
+pub fn set_circuit_state(upstream: &str, value: i64) {
+    if let Some(metrics) = METRICS.get() {
+        metrics
+            .circuit_state
+            .with_label_values(&[upstream])
+            .set(value);
+    }
+}
+
+pub fn count_error(variant: &str) {
+    if let Some(metrics) = METRICS.get() {
+        metrics.app_errors.with_label_values(&[variant]).inc();
+    }
+}
+
+pub fn count_key_reload(source: &str, success: bool) {
+    if let Some(metrics) = METRICS.get() {
+        let result = if success { "success" } else { "failure" };
+
+        metrics
+            .key_reloads
+            .with_label_values(&[source, result])
+            .inc();
+    }
+}
+
+pub async fn render() -> Result<String, AppError> {
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .map_err(|_| AppError::Internal)
+}
+
+/*
+bzd.messages.TopicsService/GetTopics -> topics.get_topics
+ */
+fn rpc_name(service: &str, method: &str) -> String {
+    let service = service.rsplit('.').next().unwrap_or(service);
+    let service = service.strip_suffix("Service").unwrap_or(service);
+
+    format!("{}.{}", snake_case(service), snake_case(method))
+}
+
+fn snake_case(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 4);
+
+    for (i, ch) in value.chars().enumerate() {
+        if ch.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+
+            out.push(ch.to_ascii_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        extract::Request,
+        http::StatusCode,
+        middleware::{self, Next},
+        response::IntoResponse as _,
+        routing::get,
+    };
+    use tower::ServiceExt as _;
+
+    use super::{METRICS, init, middleware as observe};
+
+    #[tokio::test]
+    async fn rejected_before_handler_keeps_matched_route() {
+        init().unwrap();
+
+        let app = Router::new()
+            .route("/limited/{id}", get(|| async {}))
+            .layer(middleware::from_fn(|_: Request, _: Next| async {
+                StatusCode::TOO_MANY_REQUESTS.into_response()
+            }))
+            .layer(middleware::from_fn(observe));
+
+        let req = Request::get("/limited/1").body(Body::empty()).unwrap();
+
+        assert_eq!(
+            app.oneshot(req).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        let metrics = METRICS.get().unwrap();
+
+        assert_eq!(
+            metrics
+                .http_requests
+                .with_label_values(&["GET", "/limited/{id}", "429"])
+                .get(),
+            1
+        );
+    }
+}
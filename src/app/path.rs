@@ -0,0 +1,7 @@
+use axum::extract::{FromRequestParts, Path};
+
+use crate::app::error::AppError;
+
+#[derive(FromRequestParts)]
+#[from_request(via(Path), rejection(AppError))]
+pub struct AppPath<T>(pub T);
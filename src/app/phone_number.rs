@@ -0,0 +1,100 @@
+use phonenumber::Mode;
+
+use crate::app::{
+    auth::settings::PhoneNumberSettings,
+    error::{AppError, ErrorDetail},
+};
+
+pub fn normalize(
+    field: &str,
+    phone_number: &str,
+    settings: &PhoneNumberSettings,
+) -> Result<String, AppError> {
+    let invalid = |description: &str| {
+        AppError::Validation(vec![ErrorDetail {
+            field: Some(field.into()),
+            description: description.into(),
+        }])
+    };
+
+    /*
+    Международный номер без "+" ("380501234567") в регионе по умолчанию не разбирается,
+    поэтому пробуем его ещё раз как номер с кодом страны
+     */
+    let number = match phonenumber::parse(Some(settings.default_region), phone_number) {
+        Ok(number) if number.is_valid() => Ok(number),
+        result if !phone_number.trim_start().starts_with('+') => {
+            phonenumber::parse(None, format!("+{}", phone_number.trim_start()))
+                .ok()
+                .filter(|it| it.is_valid())
+                .map_or(result, Ok)
+        }
+        result => result,
+    }
+    .map_err(|_| invalid("is not a phone number"))?;
+
+    if !number.is_valid() || number.extension().is_some() {
+        return Err(invalid("is not a valid phone number"));
+    }
+
+    let region = number.country().id();
+
+    if let Some(allowed_regions) = &settings.allowed_regions
+        && !region.is_some_and(|id| allowed_regions.contains(&id))
+    {
+        return Err(invalid("region is not supported"));
+    }
+
+    Ok(number
+        .format()
+        .mode(Mode::E164)
+        .to_string()
+        .trim_start_matches('+')
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use phonenumber::country;
+
+    use super::normalize;
+    use crate::app::auth::settings::PhoneNumberSettings;
+
+    fn settings() -> PhoneNumberSettings {
+        PhoneNumberSettings {
+            default_region: country::Id::RU,
+            allowed_regions: None,
+        }
+    }
+
+    #[test]
+    fn accepts_formatted_national_number() {
+        assert_eq!(
+            normalize("phone_number", "+7 (999) 123-45-67", &settings()).unwrap(),
+            "79991234567"
+        );
+    }
+
+    #[test]
+    fn accepts_international_number_without_plus() {
+        assert_eq!(
+            normalize("phone_number", "380501234567", &settings()).unwrap(),
+            "380501234567"
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(normalize("phone_number", "0000", &settings()).is_err());
+    }
+
+    #[test]
+    fn rejects_disallowed_region() {
+        let settings = PhoneNumberSettings {
+            allowed_regions: Some(vec![country::Id::RU]),
+            ..settings()
+        };
+
+        assert!(normalize("phone_number", "+380501234567", &settings).is_err());
+    }
+}
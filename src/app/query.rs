@@ -0,0 +1,7 @@
+use axum::extract::{FromRequestParts, Query};
+
+use crate::app::error::AppError;
+
+#[derive(FromRequestParts)]
+#[from_request(via(Query), rejection(AppError))]
+pub struct AppQuery<T>(pub T);
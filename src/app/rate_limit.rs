@@ -0,0 +1,197 @@
+pub mod settings;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use crate::app::{
+    client_ip::ClientIp,
+    error::AppError,
+    rate_limit::settings::{BudgetSettings, RateLimitSettings},
+    state::AppState,
+    user::AppUser,
+};
+
+const RATE_LIMIT_LIMIT: &str = "ratelimit-limit";
+const RATE_LIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATE_LIMIT_RESET: &str = "ratelimit-reset";
+
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+    retry_after: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Arc<Self> {
+        let rate_limiter = Arc::new(Self {
+            settings,
+            buckets: Mutex::new(HashMap::new()),
+        });
+
+        tokio::spawn({
+            let cleanup_interval = rate_limiter.settings.cleanup_interval;
+            let rate_limiter = Arc::downgrade(&rate_limiter);
+
+            async move {
+                let mut interval = tokio::time::interval(cleanup_interval);
+
+                loop {
+                    interval.tick().await;
+
+                    let Some(rate_limiter) = rate_limiter.upgrade() else {
+                        break;
+                    };
+
+                    rate_limiter.evict(Instant::now());
+                }
+            }
+        });
+
+        rate_limiter
+    }
+
+    fn group(&self, method: &Method, path: &str) -> (&str, &BudgetSettings) {
+        self.settings
+            .groups
+            .iter()
+            .find(|it| {
+                it.method
+                    .as_ref()
+                    .is_none_or(|it| it.eq_ignore_ascii_case(method.as_str()))
+                    && is_prefix(&it.path, path)
+            })
+            .map(|it| (it.name.as_str(), &it.budget))
+            .unwrap_or(("default", &self.settings.default))
+    }
+
+    fn check(&self, key: String, budget: &BudgetSettings) -> Result<Decision, AppError> {
+        let now = Instant::now();
+        let capacity = f64::from(budget.capacity);
+        let rate = capacity / budget.period.as_secs_f64();
+
+        let mut buckets = self.buckets.lock().map_err(|_| AppError::Internal)?;
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+
+        bucket.tokens = (bucket.tokens
+            + now.duration_since(bucket.updated_at).as_secs_f64() * rate)
+            .min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(Decision {
+            allowed,
+            limit: budget.capacity,
+            remaining: bucket.tokens as u32,
+            reset: Duration::from_secs_f64((capacity - bucket.tokens) / rate),
+            retry_after: Duration::from_secs_f64((1.0 - bucket.tokens).max(0.0) / rate),
+        })
+    }
+
+    /*
+    Бакет, который простоял без запросов дольше period, уже полный —
+    хранить его нет смысла, он заново создастся полным
+     */
+    fn evict(&self, now: Instant) {
+        let period = self
+            .settings
+            .groups
+            .iter()
+            .map(|it| it.budget.period)
+            .chain([self.settings.default.period])
+            .max()
+            .unwrap_or_default();
+
+        if let Ok(mut buckets) = self.buckets.lock() {
+            buckets.retain(|_, it| now.duration_since(it.updated_at) < period);
+        }
+    }
+}
+
+pub async fn middleware(
+    State(AppState { rate_limiter, .. }): State<AppState>,
+    user: Option<AppUser>,
+    ClientIp(ip): ClientIp,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (group, budget) = rate_limiter.group(req.method(), req.uri().path());
+
+    let key = match user {
+        Some(user) => format!("{group}:user:{}", user.user_id),
+        None => format!("{group}:ip:{ip}"),
+    };
+
+    let decision = rate_limiter.check(key, budget)?;
+
+    let mut res = if decision.allowed {
+        next.run(req).await
+    } else {
+        AppError::TooManyRequests(decision.retry_after).into_response()
+    };
+
+    insert_headers(res.headers_mut(), &decision);
+
+    Ok(res)
+}
+
+/*
+Префикс сравниваем по целым сегментам: /api/users покрывает /api/users/1, но не /api/usersX
+ */
+fn is_prefix(prefix: &str, path: &str) -> bool {
+    path.strip_prefix(prefix.trim_end_matches('/'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(
+        RATE_LIMIT_RESET,
+        HeaderValue::from(decision.reset.as_secs_f64().ceil() as u64),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_prefix;
+
+    #[test]
+    fn prefix_matches_whole_segments() {
+        assert!(is_prefix("/api/users", "/api/users"));
+        assert!(is_prefix("/api/users", "/api/users/1"));
+        assert!(is_prefix("/api/users/", "/api/users/1"));
+        assert!(!is_prefix("/api/users", "/api/usersX"));
+        assert!(!is_prefix("/api/users", "/api"));
+    }
+}
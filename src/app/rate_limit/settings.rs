@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct RateLimitSettings {
+    #[serde(with = "humantime_serde")]
+    pub cleanup_interval: Duration,
+    pub default: BudgetSettings,
+    pub groups: Vec<GroupSettings>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GroupSettings {
+    pub name: String,
+    pub method: Option<String>,
+    pub path: String,
+    #[serde(flatten)]
+    pub budget: BudgetSettings,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BudgetSettings {
+    pub capacity: u32,
+    #[serde(with = "humantime_serde")]
+    pub period: Duration,
+}
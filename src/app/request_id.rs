@@ -0,0 +1,48 @@
+use axum::{
+    extract::Request,
+    http::{HeaderValue, header::HeaderName},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{Instrument as _, info_span};
+use uuid::Uuid;
+
+pub const HEADER: &str = "x-request-id";
+
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: Option<String>;
+}
+
+/*
+Клиентский id принимаем только если он короткий и из печатных ASCII-символов,
+иначе генерируем свой — он попадает в логи и в метаданные gRPC
+ */
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(HEADER)
+        .and_then(|it| it.to_str().ok())
+        .filter(|it| !it.is_empty() && it.len() <= MAX_LEN)
+        .map(Into::into)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = info_span!("request", request_id = %request_id);
+
+    let mut res = REQUEST_ID
+        .scope(Some(request_id.clone()), next.run(req))
+        .instrument(span)
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(HEADER), value);
+    }
+
+    res
+}
+
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok().flatten()
+}
@@ -1,17 +1,45 @@
+use std::time::Duration;
+
 use bzd_lib::settings::Settings;
 
 use bzd_lib::settings::HttpSettings;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
-use crate::app::auth::settings::AuthSettings;
+use crate::app::{
+    admin::settings::AdminSettings, auth::settings::AuthSettings, health::settings::HealthSettings,
+    rate_limit::settings::RateLimitSettings, telemetry::settings::TelemetrySettings,
+    users::settings::UsersSettings,
+};
 
 #[derive(Deserialize, Clone)]
 pub struct AppSettings {
     pub http: HttpSettings,
+    pub admin: AdminSettings,
+    pub proxy: ProxySettings,
+    pub health: HealthSettings,
+    pub shutdown: ShutdownSettings,
+    pub deadline: DeadlineSettings,
     pub auth: AuthSettings,
+    pub rate_limit: RateLimitSettings,
+    pub telemetry: TelemetrySettings,
+    pub users: UsersSettings,
     pub clients: ClientsSettings,
 }
 
+#[derive(Deserialize, Clone)]
+pub struct ProxySettings {
+    pub trust_forwarded_for: bool,
+    pub trusted_hops: usize,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ShutdownSettings {
+    #[serde(with = "humantime_serde")]
+    pub pre_stop_delay: Duration,
+    #[serde(with = "humantime_serde")]
+    pub drain_timeout: Duration,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct ClientsSettings {
     pub bzd_users: ClientSettings,
@@ -20,7 +48,140 @@ pub struct ClientsSettings {
 
 #[derive(Deserialize, Clone)]
 pub struct ClientSettings {
-    pub endpoint: String,
+    #[serde(default, alias = "endpoint", deserialize_with = "one_or_many")]
+    pub endpoints: Vec<String>,
+    pub discovery: Option<DiscoverySettings>,
+    #[serde(with = "humantime_serde")]
+    pub health_check_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub connect_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    #[serde(default, with = "humantime_serde")]
+    pub tcp_keepalive: Option<Duration>,
+    pub tcp_nodelay: bool,
+    pub keepalive: Option<KeepaliveSettings>,
+    pub retry: Option<RetrySettings>,
+    pub breaker: Option<BreakerSettings>,
+    pub tls: Option<TlsSettings>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DiscoverySettings {
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    pub resolve_dns: bool,
+    pub health_check: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct KeepaliveSettings {
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub while_idle: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RetrySettings {
+    pub max_attempts: u32,
+    #[serde(with = "humantime_serde")]
+    pub base_backoff: Duration,
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,
+    #[serde(with = "humantime_serde")]
+    pub budget_ttl: Duration,
+    pub budget_min_per_sec: u32,
+    pub budget_percent: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct BreakerSettings {
+    pub failure_threshold: u32,
+    #[serde(with = "humantime_serde")]
+    pub open_duration: Duration,
+    pub half_open_max_calls: u32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TlsSettings {
+    pub ca_file: Option<String>,
+    pub cert_file: Option<String>,
+    pub key_file: Option<String>,
+    pub domain_name: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DeadlineSettings {
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
 }
 
 impl Settings<AppSettings> for AppSettings {}
+
+/*
+Старые конфиги задают один адрес в endpoint, новые — список в endpoints.
+Пустые строки отбрасываем, чтобы прежний endpoint = "" значил «адрес не задан»
+ */
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    let endpoints = match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(endpoint) => vec![endpoint],
+        OneOrMany::Many(endpoints) => endpoints,
+    };
+
+    Ok(endpoints.into_iter().filter(|it| !it.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+
+    use super::ClientSettings;
+
+    fn client(endpoints: &str) -> ClientSettings {
+        let toml = format!(
+            "{endpoints}
+            health_check_interval = '10s'
+            connect_timeout = '1s'
+            timeout = '5s'
+            tcp_nodelay = true"
+        );
+
+        Config::builder()
+            .add_source(File::from_str(&toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn accepts_single_endpoint() {
+        assert_eq!(
+            client("endpoint = 'http://users:50051'").endpoints,
+            ["http://users:50051"]
+        );
+    }
+
+    #[test]
+    fn accepts_endpoint_list() {
+        assert_eq!(
+            client("endpoints = ['http://users-0:50051', 'http://users-1:50051']").endpoints,
+            ["http://users-0:50051", "http://users-1:50051"]
+        );
+    }
+
+    #[test]
+    fn treats_empty_endpoint_as_missing() {
+        assert!(client("endpoint = ''").endpoints.is_empty());
+        assert!(client("").endpoints.is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    future::IntoFuture,
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use bzd_lib::error::Error;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::watch,
+};
+use tracing::{info, warn};
+
+use crate::app::state::AppState;
+
+/*
+started — сигнал получен и /readyz уже отвечает 503, stopping — серверы перестают
+принимать соединения. Между ними pre_stop_delay: балансировщик успевает заметить
+неготовность и перестать слать трафик, пока под ещё его обслуживает
+ */
+pub struct Shutdown {
+    started: watch::Sender<bool>,
+    stopping: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+}
+
+impl Shutdown {
+    pub fn new(pre_stop_delay: Duration) -> Arc<Self> {
+        let shutdown = Arc::new(Self {
+            started: watch::Sender::new(false),
+            stopping: watch::Sender::new(false),
+            in_flight: AtomicUsize::new(0),
+        });
+
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+
+            async move {
+                if let Err(err) = wait_for_signal().await {
+                    warn!("shutdown: failed to listen for signals: {}", err);
+
+                    return;
+                }
+
+                shutdown.stop(pre_stop_delay).await;
+            }
+        });
+
+        shutdown
+    }
+
+    async fn stop(&self, pre_stop_delay: Duration) {
+        info!(
+            "shutdown: started, waiting {:?} before stopping servers",
+            pre_stop_delay
+        );
+        self.started.send_replace(true);
+
+        tokio::time::sleep(pre_stop_delay).await;
+
+        info!(
+            "shutdown: stopping servers, {} requests in flight",
+            self.in_flight()
+        );
+        self.stopping.send_replace(true);
+    }
+
+    pub fn is_started(&self) -> bool {
+        *self.started.borrow()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub async fn wait(self: Arc<Self>) {
+        let mut stopping = self.stopping.subscribe();
+
+        let _ = stopping.wait_for(|it| *it).await;
+    }
+
+    /*
+    with_graceful_shutdown ждёт все соединения бесконечно, поэтому после сигнала
+    даём серверу не больше drain_timeout, а оставшиеся запросы считаем брошенными
+     */
+    pub async fn drain<F>(self: Arc<Self>, server: F, drain_timeout: Duration) -> Result<(), Error>
+    where
+        F: IntoFuture<Output = io::Result<()>>,
+    {
+        let deadline = async {
+            self.clone().wait().await;
+            tokio::time::sleep(drain_timeout).await;
+        };
+
+        tokio::select! {
+            res = server => res?,
+            _ = deadline => {}
+        }
+
+        match self.in_flight() {
+            0 => info!("shutdown: finished, no requests dropped"),
+            dropped => warn!("shutdown: finished, {} requests dropped", dropped),
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn middleware(
+    State(AppState { shutdown, .. }): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    shutdown.in_flight.fetch_add(1, Ordering::Relaxed);
+
+    let _guard = InFlight(&shutdown);
+
+    next.run(req).await
+}
+
+struct InFlight<'a>(&'a Shutdown);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+async fn wait_for_signal() -> Result<(), io::Error> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = interrupt.recv() => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Shutdown;
+
+    #[tokio::test]
+    async fn servers_stop_after_pre_stop_delay() {
+        let shutdown = Shutdown::new(Duration::ZERO);
+
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+
+            async move { shutdown.stop(Duration::from_millis(200)).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(shutdown.is_started());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), shutdown.clone().wait())
+                .await
+                .is_err()
+        );
+
+        tokio::time::timeout(Duration::from_secs(1), shutdown.clone().wait())
+            .await
+            .unwrap();
+    }
+}
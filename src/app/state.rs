@@ -8,68 +8,63 @@ use bzd_users_api::{
     auth_service_client::AuthServiceClient, contacts_service_client::ContactsServiceClient,
     sources_service_client::SourcesServiceClient, users_service_client::UsersServiceClient,
 };
-use jsonwebtoken::DecodingKey;
-use tokio::fs;
-use tonic::transport::Channel;
 
-use crate::app::{error::AppError, settings::AppSettings};
+use crate::app::{
+    auth::{
+        guard::Guard,
+        keys::DecodingKeys,
+        revocations::{MemoryRevocationStore, RevocationStore},
+        tokens::{MemoryRefreshStore, Tokens},
+    },
+    grpc::{ServiceChannel, upstream::Upstreams},
+    rate_limit::RateLimiter,
+    settings::AppSettings,
+    shutdown::Shutdown,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub settings: AppSettings,
-    pub auth_service_client: AuthServiceClient<Channel>,
-    pub users_service_client: UsersServiceClient<Channel>,
-    pub contacts_service_client: ContactsServiceClient<Channel>,
-    pub messages_service_client: MessagesServiceClient<Channel>,
-    pub topics_service_client: TopicsServiceClient<Channel>,
-    pub sources_service_client: SourcesServiceClient<Channel>,
-    pub decoding_key: Arc<DecodingKey>,
+    pub auth_service_client: AuthServiceClient<ServiceChannel>,
+    pub users_service_client: UsersServiceClient<ServiceChannel>,
+    pub contacts_service_client: ContactsServiceClient<ServiceChannel>,
+    pub messages_service_client: MessagesServiceClient<ServiceChannel>,
+    pub topics_service_client: TopicsServiceClient<ServiceChannel>,
+    pub sources_service_client: SourcesServiceClient<ServiceChannel>,
+    pub upstreams: Arc<Upstreams>,
+    pub decoding_keys: Arc<DecodingKeys>,
+    pub tokens: Option<Arc<Tokens>>,
+    pub revocations: Arc<dyn RevocationStore>,
+    pub guard: Arc<Guard>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub shutdown: Arc<Shutdown>,
 }
 
 impl AppState {
     pub async fn new(settings: AppSettings) -> Result<Self, Error> {
-        let auth_service_client = Self::create_service_client(
-            settings.clients.bzd_users.endpoint.clone(),
-            AuthServiceClient::new,
-        )
-        .await?;
-
-        let users_service_client = Self::create_service_client(
-            settings.clients.bzd_users.endpoint.clone(),
-            UsersServiceClient::new,
-        )
-        .await?;
+        let upstreams = Upstreams::new(&settings.clients).await?;
 
-        let contacts_service_client = Self::create_service_client(
-            settings.clients.bzd_users.endpoint.clone(),
-            ContactsServiceClient::new,
-        )
-        .await?;
+        let auth_service_client = AuthServiceClient::new(upstreams.bzd_users.channel.clone());
+        let users_service_client = UsersServiceClient::new(upstreams.bzd_users.channel.clone());
+        let contacts_service_client =
+            ContactsServiceClient::new(upstreams.bzd_users.channel.clone());
+        let sources_service_client = SourcesServiceClient::new(upstreams.bzd_users.channel.clone());
+        let messages_service_client =
+            MessagesServiceClient::new(upstreams.bzd_messages.channel.clone());
+        let topics_service_client =
+            TopicsServiceClient::new(upstreams.bzd_messages.channel.clone());
 
-        let sources_service_client = Self::create_service_client(
-            settings.clients.bzd_users.endpoint.clone(),
-            SourcesServiceClient::new,
+        let decoding_keys = DecodingKeys::new(&settings.auth).await?;
+        let tokens = Tokens::new(
+            &settings.auth,
+            &decoding_keys,
+            Arc::new(MemoryRefreshStore::default()),
         )
         .await?;
-
-        let messages_service_client = Self::create_service_client(
-            settings.clients.bzd_messages.endpoint.clone(),
-            MessagesServiceClient::new,
-        )
-        .await?;
-
-        let topics_service_client = Self::create_service_client(
-            settings.clients.bzd_messages.endpoint.clone(),
-            TopicsServiceClient::new,
-        )
-        .await?;
-
-        let public_key = fs::read_to_string(&settings.auth.public_key_file)
-            .await?
-            .into_bytes();
-
-        let decoding_key =
-            Arc::new(DecodingKey::from_rsa_pem(&public_key).map_err(|_| AppError::Internal)?);
+        let revocations = MemoryRevocationStore::new(settings.auth.revocation.cleanup_interval);
+        let guard = Guard::new(settings.auth.guard.clone());
+        let rate_limiter = RateLimiter::new(settings.rate_limit.clone());
+        let shutdown = Shutdown::new(settings.shutdown.pre_stop_delay);
 
         Ok(Self {
             settings,
@@ -79,16 +74,13 @@ impl AppState {
             sources_service_client,
             messages_service_client,
             topics_service_client,
-            decoding_key,
+            upstreams,
+            decoding_keys,
+            tokens,
+            revocations,
+            guard,
+            rate_limiter,
+            shutdown,
         })
     }
-
-    async fn create_service_client<T, F>(dst: String, ctor: F) -> Result<T, AppError>
-    where
-        F: FnOnce(Channel) -> T,
-    {
-        let ch = tonic::transport::Endpoint::new(dst)?.connect_lazy();
-
-        Ok(ctor(ch))
-    }
 }
@@ -0,0 +1,362 @@
+pub mod grpc;
+pub mod settings;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use bzd_lib::error::Error;
+use opentelemetry::{
+    Context, KeyValue,
+    context::FutureExt as _,
+    global,
+    propagation::{Extractor, Injector},
+    trace::{SpanKind, Status, TraceContextExt as _, Tracer as _},
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig as _};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+
+use crate::app::{request_id, telemetry::settings::TelemetrySettings};
+
+const TRACER: &str = "bzd-gw";
+
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /*
+    Логи всегда настраивает bzd_lib. Спаны пишем напрямую через OpenTelemetry API,
+    минуя tracing-подписчика, поэтому экспорт в коллектор просто включается поверх
+     */
+    pub fn init(settings: &TelemetrySettings) -> Result<Self, Error> {
+        bzd_lib::tracing::init()?;
+
+        let Some(endpoint) = &settings.otlp_endpoint else {
+            return Ok(Self { provider: None });
+        };
+
+        let provider = provider(&settings.service_name, endpoint)?;
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+
+        Ok(Self {
+            provider: Some(provider),
+        })
+    }
+
+    pub fn shutdown(self) -> Result<(), Error> {
+        if let Some(provider) = self.provider {
+            provider.shutdown()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn provider(service_name: &str, endpoint: &str) -> Result<SdkTracerProvider, Error> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_owned())
+                .build(),
+        )
+        .build())
+}
+
+pub async fn middleware(req: Request, next: Next) -> Response {
+    let parent = global::get_text_map_propagator(|it| it.extract(&HeaderExtractor(req.headers())));
+
+    let tracer = global::tracer(TRACER);
+    let method = req.method().to_string();
+
+    let mut attributes = vec![
+        KeyValue::new("http.request.method", method.clone()),
+        KeyValue::new("url.path", req.uri().path().to_owned()),
+    ];
+
+    if let Some(request_id) = request_id::current() {
+        attributes.push(KeyValue::new("request_id", request_id));
+    }
+
+    /*
+    Layer роутера выполняется после роутинга, так что MatchedPath уже в запросе
+     */
+    let name = match req.extensions().get::<MatchedPath>() {
+        Some(path) => {
+            attributes.push(KeyValue::new("http.route", path.as_str().to_owned()));
+
+            format!("{method} {}", path.as_str())
+        }
+        None => method,
+    };
+
+    let span = tracer
+        .span_builder(name)
+        .with_kind(SpanKind::Server)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &parent);
+
+    let cx = parent.with_span(span);
+
+    let res = next.run(req).with_context(cx.clone()).await;
+
+    let span = cx.span();
+
+    span.set_attribute(KeyValue::new(
+        "http.response.status_code",
+        i64::from(res.status().as_u16()),
+    ));
+
+    if res.status().is_server_error() {
+        span.set_status(Status::error(res.status().to_string()));
+    }
+
+    span.end();
+
+    res
+}
+
+pub fn record_user_id(user_id: &str) {
+    Context::map_current(|cx| {
+        cx.span()
+            .set_attribute(KeyValue::new("user_id", user_id.to_owned()))
+    });
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|it| it.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+pub(crate) struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::Request,
+        http::{HeaderMap, Response},
+        middleware,
+        routing::{any, get},
+    };
+    use http_body_util::{BodyExt as _, Full};
+    use opentelemetry::{
+        Context,
+        context::FutureExt as _,
+        global,
+        trace::{SpanId, TraceContextExt as _, TraceId, Tracer as _},
+    };
+    use opentelemetry_proto::tonic::{
+        collector::trace::v1::{ExportTraceServiceRequest, ExportTraceServiceResponse},
+        common::v1::any_value::Value,
+        trace::v1::{Span, status::StatusCode},
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use tonic::server::{Grpc, UnaryService};
+    use tonic_health::pb::{HealthCheckRequest, HealthCheckResponse, health_client::HealthClient};
+    use tonic_prost::ProstCodec;
+    use tower::{Layer as _, ServiceBuilder, ServiceExt as _, service_fn};
+
+    use super::{grpc::TraceLayer, middleware as trace, provider};
+    use crate::app::grpc::{
+        balance,
+        testing::{client, reply, serve},
+    };
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const PARENT_ID: &str = "b7ad6b7169203331";
+
+    /*
+    Заглушка OTLP-коллектора: складывает все присланные спаны в общий список
+     */
+    #[derive(Clone, Default)]
+    struct Collector(Arc<Mutex<Vec<Span>>>);
+
+    impl UnaryService<ExportTraceServiceRequest> for Collector {
+        type Response = ExportTraceServiceResponse;
+        type Future = std::future::Ready<Result<tonic::Response<Self::Response>, tonic::Status>>;
+
+        fn call(&mut self, req: tonic::Request<ExportTraceServiceRequest>) -> Self::Future {
+            let spans = req
+                .into_inner()
+                .resource_spans
+                .into_iter()
+                .flat_map(|it| it.scope_spans)
+                .flat_map(|it| it.spans);
+
+            self.0.lock().unwrap().extend(spans);
+
+            std::future::ready(Ok(tonic::Response::new(
+                ExportTraceServiceResponse::default(),
+            )))
+        }
+    }
+
+    /*
+    Провайдер глобальный, поэтому оба сценария живут в одном тесте; чужие спаны
+    из параллельных тестов отсекаем по trace_id
+     */
+    #[tokio::test(flavor = "multi_thread")]
+    async fn exports_linked_spans_with_status_from_trailers() {
+        let collector = Collector::default();
+        let router = Router::new().fallback(any({
+            let collector = collector.clone();
+
+            move |req: Request| async move {
+                Grpc::new(ProstCodec::<
+                    ExportTraceServiceResponse,
+                    ExportTraceServiceRequest,
+                >::default())
+                .unary(collector, req)
+                .await
+            }
+        }));
+
+        let provider = provider("bzd-gw", &format!("http://{}", serve(router, None))).unwrap();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+
+        let upstream = axum::Router::new().fallback(any(|req: Request| async move {
+            reply::<HealthCheckRequest, _>(req, HealthCheckResponse { status: 1 }).await
+        }));
+
+        let (channel, _) =
+            balance::channel(&client(&[format!("http://{}", serve(upstream, None))]))
+                .await
+                .unwrap();
+        let health = HealthClient::new(ServiceBuilder::new().layer(TraceLayer).service(channel));
+
+        let app = Router::new()
+            .route(
+                "/things/{id}",
+                get(move || async move {
+                    health
+                        .clone()
+                        .check(HealthCheckRequest::default())
+                        .await
+                        .unwrap();
+                }),
+            )
+            .layer(middleware::from_fn(trace));
+
+        let req = Request::get("/things/42")
+            .header("traceparent", format!("00-{TRACE_ID}-{PARENT_ID}-01"))
+            .body(axum::body::Body::empty())
+            .unwrap();
+
+        assert!(app.oneshot(req).await.unwrap().status().is_success());
+
+        /*
+        Статус приходит только в трейлерах, заголовки ответа его не содержат
+         */
+        let failing = TraceLayer.layer(service_fn(|_: Request<tonic::body::Body>| async {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", "5".parse().unwrap());
+
+            let body = Full::new(Bytes::new()).with_trailers(async move { Some(Ok(trailers)) });
+
+            Ok::<_, Infallible>(Response::new(tonic::body::Body::new(body)))
+        }));
+
+        let root = Context::current_with_span(global::tracer("test").start("root"));
+        let failing_trace_id = root.span().span_context().trace_id();
+
+        let res = failing
+            .oneshot(
+                Request::post("/pkg.Service/Method")
+                    .body(tonic::body::Body::empty())
+                    .unwrap(),
+            )
+            .with_context(root)
+            .await
+            .unwrap();
+
+        res.into_body().collect().await.unwrap();
+
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let spans = collector.0.lock().unwrap().clone();
+        let trace_id = TraceId::from_hex(TRACE_ID).unwrap().to_bytes().to_vec();
+
+        let server = find(&spans, &trace_id, "GET /things/{id}");
+        let check = find(&spans, &trace_id, "grpc.health.v1.Health/Check");
+
+        assert_eq!(
+            server.parent_span_id,
+            SpanId::from_hex(PARENT_ID).unwrap().to_bytes()
+        );
+        assert_eq!(
+            attribute(server, "http.route"),
+            Some(Value::StringValue("/things/{id}".into()))
+        );
+        assert_eq!(check.parent_span_id, server.span_id);
+        assert_eq!(
+            attribute(check, "rpc.grpc.status_code"),
+            Some(Value::IntValue(0))
+        );
+
+        let failed = find(&spans, &failing_trace_id.to_bytes(), "pkg.Service/Method");
+
+        assert_eq!(
+            attribute(failed, "rpc.grpc.status_code"),
+            Some(Value::IntValue(5))
+        );
+        assert_eq!(
+            failed.status.as_ref().map(|it| it.code),
+            Some(StatusCode::Error as i32)
+        );
+    }
+
+    fn find<'a>(spans: &'a [Span], trace_id: &[u8], name: &str) -> &'a Span {
+        spans
+            .iter()
+            .find(|it| it.trace_id == trace_id && it.name == name)
+            .unwrap_or_else(|| panic!("span {name} not exported"))
+    }
+
+    fn attribute(span: &Span, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|it| it.key == key)
+            .and_then(|it| it.value.clone())
+            .and_then(|it| it.value)
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Instant,
+};
+
+use axum::{
+    body::Bytes,
+    http::{Request, Response},
+};
+use http_body::{Frame, SizeHint};
+use opentelemetry::{
+    KeyValue,
+    context::FutureExt as _,
+    global,
+    trace::{SpanKind, Status, TraceContextExt as _, Tracer as _},
+};
+use tonic::body::Body;
+use tower::{Layer, Service};
+
+use crate::app::{
+    grpc, metrics,
+    telemetry::{HeaderInjector, TRACER},
+};
+
+#[derive(Clone)]
+pub struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = Trace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Trace { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct Trace<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for Trace<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let (service, method) = req
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .split_once('/')
+            .map(|(service, method)| (service.to_owned(), method.to_owned()))
+            .unwrap_or_default();
+
+        let tracer = global::tracer(TRACER);
+        let parent = opentelemetry::Context::current();
+
+        let span = tracer
+            .span_builder(format!("{service}/{method}"))
+            .with_kind(SpanKind::Client)
+            .with_attributes([
+                KeyValue::new("rpc.system", "grpc"),
+                KeyValue::new("rpc.service", service.clone()),
+                KeyValue::new("rpc.method", method.clone()),
+            ])
+            .start_with_context(&tracer, &parent);
+
+        let cx = parent.with_span(span);
+
+        global::get_text_map_propagator(|it| {
+            it.inject_context(&cx, &mut HeaderInjector(req.headers_mut()))
+        });
+
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+
+        let call = Call {
+            cx: cx.clone(),
+            service,
+            method,
+            started_at: Instant::now(),
+        };
+
+        Box::pin(
+            async move {
+                let res = match inner.call(req).await {
+                    Ok(res) => res,
+                    Err(err) => {
+                        call.finish(None);
+
+                        return Err(err);
+                    }
+                };
+
+                /*
+                Ошибки tonic-сервер отдаёт trailers-only ответом со статусом в заголовках,
+                а обычный ответ несёт grpc-status в трейлерах — их дожидаемся в теле
+                 */
+                if let Some(code) = grpc::response_code(&res) {
+                    call.finish(Some(code));
+
+                    return Ok(res);
+                }
+
+                Ok(res.map(|body| {
+                    Body::new(StatusBody {
+                        inner: body,
+                        call: Some(call),
+                    })
+                }))
+            }
+            .with_context(cx),
+        )
+    }
+}
+
+struct Call {
+    cx: opentelemetry::Context,
+    service: String,
+    method: String,
+    started_at: Instant,
+}
+
+impl Call {
+    /*
+    None — до статуса дело не дошло: транспортная ошибка или тело бросили на середине
+     */
+    fn finish(self, code: Option<tonic::Code>) {
+        let span = self.cx.span();
+
+        let label = match code {
+            Some(code) => {
+                span.set_attribute(KeyValue::new("rpc.grpc.status_code", code as i64));
+
+                if code != tonic::Code::Ok {
+                    span.set_status(Status::error(format!("{code:?}")));
+                }
+
+                format!("{code:?}")
+            }
+            None => {
+                span.set_status(Status::error("transport_error"));
+
+                "transport_error".into()
+            }
+        };
+
+        metrics::observe_grpc(&self.service, &self.method, &label, self.started_at);
+
+        span.end();
+    }
+}
+
+struct StatusBody {
+    inner: Body,
+    call: Option<Call>,
+}
+
+impl http_body::Body for StatusBody {
+    type Data = Bytes;
+    type Error = tonic::Status;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let frame = ready!(Pin::new(&mut self.inner).poll_frame(cx));
+
+        let code = match &frame {
+            Some(Ok(frame)) => frame.trailers_ref().map(grpc::status_code),
+            Some(Err(status)) => Some(Some(status.code())),
+            None => Some(None),
+        };
+
+        if let Some(code) = code
+            && let Some(call) = self.call.take()
+        {
+            call.finish(code);
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for StatusBody {
+    fn drop(&mut self) {
+        if let Some(call) = self.call.take() {
+            call.finish(None);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct TelemetrySettings {
+    pub service_name: String,
+    pub otlp_endpoint: Option<String>,
+}
@@ -5,10 +5,11 @@ use axum::{
 };
 use bzd_messages_api::{
     CreateTopicRequest, CreateTopicUserRequest, DeleteTopicUserRequest, GetTopicRequest,
-    GetTopicsRequest,
+    GetTopicsRequest, GetTopicsUsersRequest,
 };
+use bzd_users_api::GetUsersRequest;
 
-use crate::app::{error::AppError, json::AppJson, state::AppState, user::AppUser};
+use crate::app::{error::AppError, json::AppJson, path::AppPath, state::AppState, user::AppUser};
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -16,6 +17,7 @@ pub fn router() -> Router<AppState> {
         .route("/", post(create_topic))
         .route("/users", post(create_topic_user))
         .route("/users", delete(delete_topic_user))
+        .route("/{topic_id}", get(get_topic))
 }
 
 async fn get_topics(
@@ -71,6 +73,153 @@ mod get_topics {
     }
 }
 
+async fn get_topic(
+    State(AppState {
+        topics_service_client,
+        users_service_client,
+        ..
+    }): State<AppState>,
+    user: AppUser,
+    AppPath(topic_id): AppPath<String>,
+) -> Result<AppJson<get_topic::Response>, AppError> {
+    let get_topic_response = topics_service_client
+        .clone()
+        .get_topic(GetTopicRequest {
+            topic_id: Some(topic_id.clone()),
+            user_id: Some(user.user_id.clone()),
+        })
+        .await?
+        .into_inner();
+
+    let owner_id = get_topic_response
+        .topic
+        .as_ref()
+        .ok_or(AppError::Internal)?
+        .user_id()
+        .to_string();
+
+    /*
+    Владелец и подписка вызывающего зависят только от топика, поэтому идут параллельно
+     */
+    let (get_users_response, get_topics_users_response) = tokio::try_join!(
+        async {
+            users_service_client
+                .clone()
+                .get_users(GetUsersRequest {
+                    user_ids: vec![owner_id],
+                })
+                .await
+        },
+        async {
+            topics_service_client
+                .clone()
+                .get_topics_users(GetTopicsUsersRequest {
+                    topic_ids: vec![topic_id],
+                    user_id: Some(user.user_id),
+                })
+                .await
+        },
+    )?;
+
+    Ok(AppJson(
+        (
+            get_topic_response,
+            get_users_response.into_inner(),
+            get_topics_users_response.into_inner(),
+        )
+            .try_into()?,
+    ))
+}
+
+mod get_topic {
+    use bzd_messages_api::{GetTopicResponse, GetTopicsUsersResponse};
+    use bzd_users_api::GetUsersResponse;
+    use serde::Serialize;
+    use tracing::warn;
+
+    use crate::app::error::AppError;
+
+    #[derive(Serialize)]
+    pub struct Response {
+        pub topic: Topic,
+    }
+
+    #[derive(Serialize)]
+    pub struct Topic {
+        pub topic_id: String,
+        pub title: String,
+        pub user: Option<User>,
+        pub topic_user: Option<TopicUser>,
+    }
+
+    /*
+    Топик виден любому, кто знает topic_id, поэтому телефон владельца не отдаём
+     */
+    #[derive(Serialize)]
+    pub struct User {
+        pub user_id: String,
+        pub name: String,
+        pub abbr: String,
+        pub color: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct TopicUser {
+        pub topic_user_id: String,
+    }
+
+    type Responses = (GetTopicResponse, GetUsersResponse, GetTopicsUsersResponse);
+
+    impl TryFrom<Responses> for Response {
+        type Error = AppError;
+
+        fn try_from(
+            (get_topic_response, get_users_response, get_topics_users_response): Responses,
+        ) -> Result<Self, Self::Error> {
+            let topic = get_topic_response.topic.ok_or(AppError::Internal)?;
+
+            /*
+            Владельца могли удалить, сам топик от этого не ломается
+             */
+            let user = get_users_response
+                .users
+                .into_iter()
+                .find(|it| it.user_id() == topic.user_id())
+                .map(|user| User {
+                    user_id: user.user_id().into(),
+                    name: user.name().into(),
+                    abbr: user.abbr().into(),
+                    color: user.color().into(),
+                });
+
+            if user.is_none() {
+                warn!(
+                    "topics: owner {} of topic {} not found",
+                    topic.user_id(),
+                    topic.topic_id()
+                );
+            }
+
+            let topic_user = get_topics_users_response
+                .topics_users
+                .into_iter()
+                .find(|it| it.topic_id() == topic.topic_id())
+                .map(|it| TopicUser {
+                    topic_user_id: it.topic_user_id().into(),
+                });
+
+            Ok(Self {
+                topic: Topic {
+                    topic_id: topic.topic_id().into(),
+                    title: topic.title().into(),
+                    user,
+                    topic_user,
+                },
+            })
+        }
+    }
+}
+
 async fn create_topic(
     State(AppState {
         topics_service_client,
@@ -246,3 +395,41 @@ mod delete_topic_user {
     #[derive(Serialize)]
     pub struct Response {}
 }
+
+#[cfg(test)]
+mod tests {
+    use bzd_messages_api::{GetTopicResponse, GetTopicsUsersResponse, get_topic_response};
+    use bzd_users_api::GetUsersResponse;
+
+    use super::get_topic;
+
+    /*
+    В прото полей может быть больше, чем нужно тесту, поэтому добиваем дефолтами
+     */
+    #[allow(clippy::needless_update)]
+    fn topic() -> GetTopicResponse {
+        GetTopicResponse {
+            topic: Some(get_topic_response::Topic {
+                topic_id: Some("topic".into()),
+                title: Some("Topic".into()),
+                user_id: Some("owner".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn missing_owner_keeps_topic() {
+        let res: get_topic::Response = (
+            topic(),
+            GetUsersResponse::default(),
+            GetTopicsUsersResponse::default(),
+        )
+            .try_into()
+            .unwrap();
+
+        assert_eq!(res.topic.topic_id, "topic");
+        assert!(res.topic.user.is_none());
+    }
+}
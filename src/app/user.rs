@@ -1,21 +1,74 @@
 use axum_extra::headers::authorization::Bearer;
-use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation, decode};
-use serde::Deserialize;
+use jsonwebtoken::{Algorithm, TokenData, Validation, decode, decode_header, errors::ErrorKind};
+use serde::{Deserialize, Serialize};
 
-use crate::app::error::AppError;
+use crate::app::{
+    auth::{keys::DecodingKeys, revocations::RevocationStore, settings::AuthSettings},
+    error::AppError,
+};
 
-fn jwt_2_user(bearer: Bearer, decoding_key: &DecodingKey) -> Result<AppUser, AppError> {
-    let TokenData { claims, .. } = decode::<Claims>(
-        bearer.token(),
-        decoding_key,
-        &Validation::new(Algorithm::RS256),
-    )?;
+async fn jwt_2_user(
+    bearer: Bearer,
+    decoding_keys: &DecodingKeys,
+    revocations: &dyn RevocationStore,
+    settings: &AuthSettings,
+) -> Result<AppUser, AppError> {
+    let claims = decode_claims(bearer.token(), decoding_keys, settings)?;
+
+    if revocations.is_revoked(&claims).await? {
+        return Err(AppError::TokenRevoked);
+    }
 
     Ok(AppUser {
         user_id: claims.sub,
+        jti: claims.jti,
+        expires_at: claims.exp,
     })
 }
 
+pub fn decode_claims(
+    token: &str,
+    decoding_keys: &DecodingKeys,
+    settings: &AuthSettings,
+) -> Result<Claims, AppError> {
+    let header = decode_header(token)?;
+    let validation = validation(settings);
+
+    let mut token_data = Err(AppError::UnknownKey);
+
+    for decoding_key in decoding_keys.find(header.kid.as_deref()) {
+        token_data = decode::<Claims>(token, &decoding_key, &validation).map_err(Into::into);
+
+        match &token_data {
+            Err(AppError::Jwt(err)) if *err.kind() == ErrorKind::InvalidSignature => continue,
+            _ => break,
+        }
+    }
+
+    let TokenData { claims, .. } = token_data?;
+
+    Ok(claims)
+}
+
+fn validation(settings: &AuthSettings) -> Validation {
+    let mut validation = Validation::new(Algorithm::RS256);
+
+    validation.leeway = settings.leeway.as_secs();
+    validation.validate_nbf = true;
+    validation.set_required_spec_claims(&["exp", "sub"]);
+
+    if let Some(issuer) = &settings.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+
+    match &settings.audience {
+        Some(audience) => validation.set_audience(audience),
+        None => validation.validate_aud = false,
+    }
+
+    validation
+}
+
 mod jwt_2_user {
     use axum::{
         RequestPartsExt as _,
@@ -30,6 +83,7 @@ mod jwt_2_user {
     use crate::app::{
         error::AppError,
         state::AppState,
+        telemetry,
         user::{AppUser, jwt_2_user},
     };
 
@@ -41,13 +95,29 @@ mod jwt_2_user {
         type Rejection = AppError;
 
         async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-            let AppState { decoding_key, .. } = AppState::from_ref(state);
+            /*
+            Пользователя уже мог извлечь rate limiter — не проверяем JWT и отзыв повторно
+             */
+            if let Some(user) = parts.extensions.get::<AppUser>() {
+                return Ok(user.clone());
+            }
+
+            let AppState {
+                decoding_keys,
+                revocations,
+                settings,
+                ..
+            } = AppState::from_ref(state);
 
             let TypedHeader(Authorization(bearer)) = parts
                 .extract::<TypedHeader<Authorization<Bearer>>>()
                 .await?;
 
-            let user = jwt_2_user(bearer, &decoding_key)?;
+            let user =
+                jwt_2_user(bearer, &decoding_keys, revocations.as_ref(), &settings.auth).await?;
+
+            telemetry::record_user_id(&user.user_id);
+            parts.extensions.insert(user.clone());
 
             Ok(user)
         }
@@ -72,13 +142,32 @@ mod jwt_2_user {
     }
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
-    // pub exp: usize,
+    pub exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct AppUser {
     pub user_id: String,
+    pub jti: Option<String>,
+    pub expires_at: u64,
 }
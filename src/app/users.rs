@@ -1,10 +1,23 @@
-use std::collections::HashSet;
+pub mod settings;
 
-use axum::{Router, extract::State, routing::get};
-use bzd_messages_api::{GetTopicsRequest, GetTopicsUsersRequest};
-use bzd_users_api::{GetSourcesRequest, GetUsersRequest};
+use std::{collections::HashSet, time::Duration};
 
-use crate::app::{error::AppError, json::AppJson, state::AppState, user::AppUser};
+use axum::{Router, extract::State, routing::get};
+use bzd_messages_api::{
+    GetTopicsRequest, GetTopicsResponse, GetTopicsUsersRequest, GetTopicsUsersResponse,
+    topics_service_client::TopicsServiceClient,
+};
+use bzd_users_api::{
+    GetSourcesRequest, GetUsersRequest, sources_service_client::SourcesServiceClient,
+    users_service_client::UsersServiceClient,
+};
+
+use tracing::warn;
+
+use crate::app::{
+    error::AppError, grpc::ServiceChannel, json::AppJson, query::AppQuery, state::AppState,
+    user::AppUser,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new().route("/", get(get_users))
@@ -12,77 +25,160 @@ pub fn router() -> Router<AppState> {
 
 async fn get_users(
     State(AppState {
+        settings,
         sources_service_client,
         users_service_client,
         topics_service_client,
         ..
     }): State<AppState>,
     user: AppUser,
+    AppQuery(req): AppQuery<get_users::Request>,
 ) -> Result<AppJson<get_users::Response>, AppError> {
+    let page = get_users::Page::new(req, &settings.users)?;
+
+    let responses = fetch(
+        &sources_service_client,
+        &users_service_client,
+        &topics_service_client,
+        &user.user_id,
+        page,
+        settings.users.branch_timeout,
+    )
+    .await?;
+
+    Ok(AppJson(responses.try_into()?))
+}
+
+async fn fetch(
+    sources_service_client: &SourcesServiceClient<ServiceChannel>,
+    users_service_client: &UsersServiceClient<ServiceChannel>,
+    topics_service_client: &TopicsServiceClient<ServiceChannel>,
+    user_id: &str,
+    mut page: get_users::Page,
+    branch_timeout: Duration,
+) -> Result<get_users::Responses, AppError> {
     let req = GetSourcesRequest {
-        user_id: Some(user.user_id.clone().into()),
+        user_id: Some(user_id.into()),
     };
 
-    let get_sources_response = sources_service_client
+    let mut get_sources_response = sources_service_client
         .clone()
         .get_sources(req)
         .await?
         .into_inner();
 
+    /*
+    Контакты режем до похода в get_users: их могут быть тысячи, а имя контакта
+    известно уже из get_sources. Источников мало, их режем при сборке ответа
+     */
+    get_sources_response.contacts = page.contacts(get_sources_response.contacts);
+
+    if !page.includes(get_users::Kind::Sources) {
+        get_sources_response.sources.clear();
+    }
+
+    let source_user_ids: HashSet<String> = get_sources_response
+        .sources
+        .iter()
+        .map(|it| it.source_user_id().into())
+        .collect();
+
     let user_ids: HashSet<String> = get_sources_response
         .contacts
         .iter()
         .map(|it| it.contact_user_id().into())
-        .chain(
-            get_sources_response
-                .sources
-                .iter()
-                .map(|it| it.source_user_id().into()),
-        )
+        .chain(source_user_ids.iter().cloned())
         .collect();
 
-    let get_users_response = users_service_client
-        .clone()
-        .get_users(GetUsersRequest {
-            user_ids: user_ids.clone().into_iter().collect(),
-        })
-        .await?
-        .into_inner();
+    /*
+    get_users и get_topics зависят только от user_ids из get_sources, поэтому идут
+    параллельно: на критическом пути остаются get_sources -> get_topics -> get_topics_users
+     */
+    let users_branch = with_timeout("get_users", branch_timeout, async {
+        Ok(users_service_client
+            .clone()
+            .get_users(GetUsersRequest {
+                user_ids: user_ids.iter().cloned().collect(),
+            })
+            .await?
+            .into_inner())
+    });
+
+    /*
+    Подписки на топики — необязательная часть ответа: если эта ветка упала,
+    отдаём источники и контакты без топиков и с предупреждением
+     */
+    let topics_branch = with_timeout("get_topics", branch_timeout, async {
+        if source_user_ids.is_empty() {
+            return Ok((
+                GetTopicsResponse::default(),
+                Some(GetTopicsUsersResponse::default()),
+            ));
+        }
 
-    let get_topics_response = topics_service_client
-        .clone()
-        .get_topics(GetTopicsRequest {
-            user_ids: user_ids.into_iter().collect(),
-        })
-        .await?
-        .into_inner();
+        let get_topics_response = topics_service_client
+            .clone()
+            .get_topics(GetTopicsRequest {
+                user_ids: source_user_ids.iter().cloned().collect(),
+            })
+            .await?
+            .into_inner();
+
+        let topic_ids: HashSet<String> = get_topics_response
+            .topics
+            .iter()
+            .map(|it| it.topic_id().into())
+            .collect();
+
+        let get_topics_users_response = topics_service_client
+            .clone()
+            .get_topics_users(GetTopicsUsersRequest {
+                topic_ids: topic_ids.into_iter().collect(),
+                user_id: Some(user_id.into()),
+            })
+            .await
+            .inspect_err(|err| warn!("users: get_topics_users failed: {}", err))
+            .ok()
+            .map(|it| it.into_inner());
 
-    let topic_ids: HashSet<String> = get_topics_response
-        .topics
-        .iter()
-        .map(|it| it.topic_id().into())
-        .collect();
+        Ok((get_topics_response, get_topics_users_response))
+    });
 
-    let get_topics_users_response = topics_service_client
-        .clone()
-        .get_topics_users(GetTopicsUsersRequest {
-            topic_ids: topic_ids.into_iter().collect(),
-            user_id: Some(user.user_id.into()),
-        })
-        .await?
-        .into_inner();
+    let (get_users_response, topics) = tokio::join!(users_branch, topics_branch);
+    let get_users_response = get_users_response?;
 
-    Ok(AppJson(
-        (
-            get_sources_response,
-            get_users_response,
-            get_topics_response,
-            get_topics_users_response,
-        )
-            .try_into()?,
+    let (get_topics_response, get_topics_users_response) = match topics {
+        Ok((get_topics_response, get_topics_users_response)) => {
+            (Some(get_topics_response), get_topics_users_response)
+        }
+        Err(err) => {
+            warn!("users: get_topics failed: {}", err);
+
+            (None, None)
+        }
+    };
+
+    Ok((
+        get_sources_response,
+        get_users_response,
+        get_topics_response,
+        get_topics_users_response,
+        page,
     ))
 }
 
+async fn with_timeout<T>(
+    branch: &str,
+    timeout: Duration,
+    fut: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    tokio::time::timeout(timeout, fut).await.map_err(|_| {
+        AppError::Status(tonic::Status::deadline_exceeded(format!(
+            "{branch}: timed out"
+        )))
+    })?
+}
+
 mod get_users {
     use std::collections::HashMap;
 
@@ -92,14 +188,182 @@ mod get_users {
     use bzd_users_api::{
         GetSourcesResponse, GetUsersResponse, get_sources_response, get_users_response,
     };
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize};
 
-    use crate::app::error::AppError;
+    use crate::app::{
+        error::{AppError, ErrorDetail},
+        users::settings::UsersSettings,
+    };
+
+    #[derive(Deserialize)]
+    pub struct Request {
+        pub kind: Option<Kind>,
+        pub q: Option<String>,
+        pub limit: Option<usize>,
+        pub cursor: Option<String>,
+    }
+
+    #[derive(Deserialize, Clone, Copy, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Kind {
+        Sources,
+        Contacts,
+    }
 
     #[derive(Serialize)]
     pub struct Response {
         pub sources: Sources,
         pub contacts: Contacts,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub next_cursor: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub warnings: Warnings,
+    }
+
+    /*
+    Выдача упорядочена так: сначала источники, потом контакты, внутри — по имени
+    без учёта регистра и по id. Курсор — последний отданный ключ в этом порядке
+     */
+    pub struct Page {
+        kind: Option<Kind>,
+        q: Option<String>,
+        limit: usize,
+        cursor: Option<Cursor>,
+        contacts_truncated: bool,
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Cursor {
+        rank: u8,
+        name: String,
+        id: String,
+    }
+
+    impl Page {
+        pub fn new(req: Request, settings: &UsersSettings) -> Result<Self, AppError> {
+            let cursor = req.cursor.map(|it| Cursor::decode(&it)).transpose()?;
+
+            Ok(Self {
+                kind: req.kind,
+                q: req
+                    .q
+                    .map(|it| it.trim().to_lowercase())
+                    .filter(|it| !it.is_empty()),
+                limit: req
+                    .limit
+                    .unwrap_or(settings.default_limit)
+                    .clamp(1, settings.max_limit.get()),
+                cursor,
+                contacts_truncated: false,
+            })
+        }
+
+        pub fn includes(&self, kind: Kind) -> bool {
+            self.kind.is_none_or(|it| it == kind)
+        }
+
+        pub fn contacts(
+            &mut self,
+            contacts: Vec<get_sources_response::Contact>,
+        ) -> Vec<get_sources_response::Contact> {
+            if !self.includes(Kind::Contacts) {
+                return vec![];
+            }
+
+            let mut contacts: Vec<_> = contacts
+                .into_iter()
+                .map(|it| (Cursor::contact(it.contact_id(), it.name()), it))
+                .filter(|(key, it)| self.matches(it.name()) && self.is_after(key))
+                .collect();
+
+            contacts.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            self.contacts_truncated = contacts.len() > self.limit;
+
+            contacts
+                .into_iter()
+                .take(self.limit)
+                .map(|(_, it)| it)
+                .collect()
+        }
+
+        fn matches(&self, name: &str) -> bool {
+            self.q
+                .as_deref()
+                .is_none_or(|q| name.to_lowercase().contains(q))
+        }
+
+        fn is_after(&self, key: &Cursor) -> bool {
+            self.cursor.as_ref().is_none_or(|cursor| key > cursor)
+        }
+    }
+
+    impl Cursor {
+        fn source(source_id: &str, name: &str) -> Self {
+            Self {
+                rank: 0,
+                name: name.to_lowercase(),
+                id: source_id.into(),
+            }
+        }
+
+        fn contact(contact_id: &str, name: &str) -> Self {
+            Self {
+                rank: 1,
+                name: name.to_lowercase(),
+                id: contact_id.into(),
+            }
+        }
+
+        fn encode(&self) -> String {
+            format!("{}\0{}\0{}", self.rank, self.name, self.id)
+                .bytes()
+                .map(|it| format!("{it:02x}"))
+                .collect()
+        }
+
+        fn decode(cursor: &str) -> Result<Self, AppError> {
+            let invalid = || {
+                AppError::Validation(vec![ErrorDetail {
+                    field: Some("cursor".into()),
+                    description: "invalid".into(),
+                }])
+            };
+
+            if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+                return Err(invalid());
+            }
+
+            let bytes = (0..cursor.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+
+            let cursor = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+            let mut parts = cursor.splitn(3, '\0');
+
+            let (Some(rank), Some(name), Some(id)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid());
+            };
+
+            Ok(Self {
+                rank: rank.parse().map_err(|_| invalid())?,
+                name: name.into(),
+                id: id.into(),
+            })
+        }
+    }
+
+    type Warnings = Vec<Warning>;
+
+    #[derive(Serialize)]
+    pub struct Warning {
+        pub code: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub id: Option<String>,
     }
 
     type Sources = Vec<Source>;
@@ -146,11 +410,12 @@ mod get_users {
     type TopicsUsers = HashMap<String, get_topics_users_response::TopicUser>;
 
     // TODO: нужно придумать имя для такой темп структуры
-    type Responses = (
+    pub type Responses = (
         GetSourcesResponse,
         GetUsersResponse,
-        GetTopicsResponse,
-        GetTopicsUsersResponse,
+        Option<GetTopicsResponse>,
+        Option<GetTopicsUsersResponse>,
+        Page,
     );
 
     impl TryFrom<Responses> for Response {
@@ -162,6 +427,7 @@ mod get_users {
                 get_users_response,
                 get_topics_response,
                 get_topics_users_response,
+                page,
             ): Responses,
         ) -> Result<Self, Self::Error> {
             let users: Users = get_users_response
@@ -170,30 +436,124 @@ mod get_users {
                 .map(|it| (it.user_id().into(), it))
                 .collect();
 
-            let topics: Topics = get_topics_response.topics;
-
-            let topics_users: TopicsUsers = get_topics_users_response
-                .topics_users
-                .into_iter()
-                .map(|it| (it.topic_id().into(), it))
-                .collect();
-
-            let contacts: Contacts = get_sources_response
-                .contacts
-                .into_iter()
-                .map(|contact| (contact, &users).try_into())
-                .collect::<Result<_, _>>()?;
-
-            let sources: Sources = get_sources_response
-                .sources
-                .into_iter()
-                .map(|source| (source, &users, &topics, &topics_users).try_into())
-                .collect::<Result<_, _>>()?;
+            let mut warnings: Warnings = vec![];
+
+            let topics: Topics = match get_topics_response {
+                Some(get_topics_response) => get_topics_response.topics,
+                None => {
+                    warnings.push(Warning {
+                        code: "TOPICS_UNAVAILABLE",
+                        id: None,
+                    });
+
+                    vec![]
+                }
+            };
+
+            let topics_users: TopicsUsers = match get_topics_users_response {
+                Some(get_topics_users_response) => get_topics_users_response
+                    .topics_users
+                    .into_iter()
+                    .map(|it| (it.topic_id().into(), it))
+                    .collect(),
+                None if !topics.is_empty() => {
+                    warnings.push(Warning {
+                        code: "TOPICS_USERS_UNAVAILABLE",
+                        id: None,
+                    });
+
+                    TopicsUsers::new()
+                }
+                None => TopicsUsers::new(),
+            };
+
+            /*
+            Страницу и курсор считаем по ключам из get_sources, до того как выкинем записи
+            без пользователя: иначе курсор уедет назад и отброшенный хвост придёт повторно.
+            Имя источника берём у пользователя, у потерянного — пустое
+             */
+            let mut entries: Vec<(Cursor, Entry)> = vec![];
+
+            for source in get_sources_response.sources {
+                let name = users
+                    .get(source.source_user_id())
+                    .map(|it| it.name())
+                    .unwrap_or_default();
+
+                if page.matches(name) {
+                    entries.push((
+                        Cursor::source(source.source_id(), name),
+                        Entry::Source(source),
+                    ));
+                }
+            }
+
+            for contact in get_sources_response.contacts {
+                entries.push((
+                    Cursor::contact(contact.contact_id(), contact.name()),
+                    Entry::Contact(contact),
+                ));
+            }
+
+            entries.retain(|(key, _)| page.is_after(key));
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let has_more = page.contacts_truncated || entries.len() > page.limit;
+
+            entries.truncate(page.limit);
+
+            let next_cursor = entries
+                .last()
+                .filter(|_| has_more)
+                .map(|(key, _)| key.encode());
+
+            /*
+            Контакт или источник без пользователя пропускаем, а не роняем всю выдачу
+             */
+            let mut sources: Sources = vec![];
+            let mut contacts: Contacts = vec![];
+
+            for (_, entry) in entries {
+                match entry {
+                    Entry::Source(source) => {
+                        let source_id = source.source_id().to_string();
+
+                        match (source, &users, &topics, &topics_users).try_into() {
+                            Ok(source) => sources.push(source),
+                            Err(_) => warnings.push(Warning {
+                                code: "SOURCE_USER_NOT_FOUND",
+                                id: Some(source_id),
+                            }),
+                        }
+                    }
+                    Entry::Contact(contact) => {
+                        let contact_id = contact.contact_id().to_string();
+
+                        match (contact, &users).try_into() {
+                            Ok(contact) => contacts.push(contact),
+                            Err(_) => warnings.push(Warning {
+                                code: "CONTACT_USER_NOT_FOUND",
+                                id: Some(contact_id),
+                            }),
+                        }
+                    }
+                }
+            }
 
-            Ok(Self { contacts, sources })
+            Ok(Self {
+                contacts,
+                sources,
+                next_cursor,
+                warnings,
+            })
         }
     }
 
+    enum Entry {
+        Source(get_sources_response::Source),
+        Contact(get_sources_response::Contact),
+    }
+
     impl TryFrom<(get_sources_response::Contact, &Users)> for Contact {
         type Error = AppError;
 
@@ -262,3 +622,231 @@ mod get_users {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        num::NonZeroUsize,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{fetch, get_users};
+    use crate::app::{
+        grpc::{
+            testing::{client, reply, serve},
+            upstream::Upstreams,
+        },
+        settings::ClientsSettings,
+        users::settings::UsersSettings,
+    };
+    use axum::{extract::Request, routing::any};
+    use bzd_messages_api::{
+        GetTopicsResponse, GetTopicsUsersResponse, get_topics_response,
+        topics_service_client::TopicsServiceClient,
+    };
+    use bzd_users_api::{
+        GetSourcesResponse, GetUsersResponse, get_sources_response, get_users_response,
+        sources_service_client::SourcesServiceClient, users_service_client::UsersServiceClient,
+    };
+
+    const DELAY: Duration = Duration::from_millis(200);
+
+    /*
+    Журнал вызовов мока: начало и конец каждого метода в порядке наступления
+     */
+    #[derive(Clone, Default)]
+    struct Calls(Arc<Mutex<Vec<(String, bool)>>>);
+
+    impl Calls {
+        fn push(&self, method: &str, finished: bool) {
+            self.0.lock().unwrap().push((method.into(), finished));
+        }
+
+        /*
+        Глубина вызова — сколько ответов апстрима пришлось последовательно дождаться,
+        чтобы его сделать: на единицу больше самой глубокой из уже завершённых
+         */
+        fn depths(&self) -> HashMap<String, usize> {
+            let mut depths = HashMap::new();
+            let mut finished = 0;
+
+            for (method, done) in self.0.lock().unwrap().iter() {
+                if *done {
+                    finished = finished.max(depths[method]);
+                } else {
+                    depths.insert(method.clone(), finished + 1);
+                }
+            }
+
+            depths
+        }
+    }
+
+    /*
+    Один мок на оба апстрима: каждый метод отвечает с задержкой, get_topics* — со своей.
+    Метод определяем по последнему сегменту пути, пакет прото тут неважен
+     */
+    async fn mock(topics_delay: Duration, calls: Calls) -> String {
+        let router = axum::Router::new().fallback(any(move |req: Request| async move {
+            let method = req
+                .uri()
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+
+            calls.push(&method, false);
+
+            tokio::time::sleep(match method.as_str() {
+                "GetTopics" | "GetTopicsUsers" => topics_delay,
+                _ => DELAY,
+            })
+            .await;
+
+            calls.push(&method, true);
+
+            match method.as_str() {
+                "GetSources" => reply::<bzd_users_api::GetSourcesRequest, _>(req, sources()).await,
+                "GetUsers" => reply::<bzd_users_api::GetUsersRequest, _>(req, users()).await,
+                "GetTopics" => reply::<bzd_messages_api::GetTopicsRequest, _>(req, topics()).await,
+                _ => {
+                    reply::<bzd_messages_api::GetTopicsUsersRequest, _>(
+                        req,
+                        GetTopicsUsersResponse::default(),
+                    )
+                    .await
+                }
+            }
+        }));
+
+        format!("http://{}", serve(router, None))
+    }
+
+    /*
+    В прото полей может быть больше, чем нужно тесту, поэтому добиваем дефолтами
+     */
+    #[allow(clippy::needless_update)]
+    fn sources() -> GetSourcesResponse {
+        GetSourcesResponse {
+            sources: vec![get_sources_response::Source {
+                source_id: Some("source".into()),
+                source_user_id: Some("owner".into()),
+                ..Default::default()
+            }],
+            contacts: vec![get_sources_response::Contact {
+                contact_id: Some("contact".into()),
+                contact_user_id: Some("friend".into()),
+                name: Some("Friend".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[allow(clippy::needless_update)]
+    fn users() -> GetUsersResponse {
+        GetUsersResponse {
+            users: ["owner", "friend"]
+                .into_iter()
+                .map(|it| get_users_response::User {
+                    user_id: Some(it.into()),
+                    name: Some(it.into()),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[allow(clippy::needless_update)]
+    fn topics() -> GetTopicsResponse {
+        GetTopicsResponse {
+            topics: vec![get_topics_response::Topic {
+                topic_id: Some("topic".into()),
+                user_id: Some("owner".into()),
+                title: Some("Topic".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    async fn get(
+        topics_delay: Duration,
+        branch_timeout: Duration,
+        calls: Calls,
+    ) -> get_users::Response {
+        let endpoint = mock(topics_delay, calls).await;
+
+        let upstreams = Upstreams::new(&ClientsSettings {
+            bzd_users: client(std::slice::from_ref(&endpoint)),
+            bzd_messages: client(&[endpoint]),
+        })
+        .await
+        .unwrap();
+
+        let page = get_users::Page::new(
+            get_users::Request {
+                kind: None,
+                q: None,
+                limit: None,
+                cursor: None,
+            },
+            &UsersSettings {
+                branch_timeout,
+                default_limit: 50,
+                max_limit: NonZeroUsize::new(200).unwrap(),
+            },
+        )
+        .unwrap();
+
+        fetch(
+            &SourcesServiceClient::new(upstreams.bzd_users.channel.clone()),
+            &UsersServiceClient::new(upstreams.bzd_users.channel.clone()),
+            &TopicsServiceClient::new(upstreams.bzd_messages.channel.clone()),
+            "me",
+            page,
+            branch_timeout,
+        )
+        .await
+        .unwrap()
+        .try_into()
+        .unwrap()
+    }
+
+    /*
+    Последовательно было бы get_sources -> get_users -> get_topics -> get_topics_users,
+    то есть глубина четыре; с параллельными ветками get_users и get_topics идут
+    вместе, и на критическом пути три вызова
+     */
+    #[tokio::test]
+    async fn critical_path_is_three_round_trips() {
+        let calls = Calls::default();
+
+        let res = get(DELAY, Duration::from_secs(5), calls.clone()).await;
+
+        let depths = calls.depths();
+
+        assert_eq!(depths.len(), 4, "{depths:?}");
+        assert_eq!(depths["GetSources"], 1);
+        assert_eq!(depths["GetUsers"], 2);
+        assert_eq!(depths["GetTopics"], 2);
+        assert_eq!(depths["GetTopicsUsers"], 3);
+        assert_eq!(res.sources.len(), 1);
+        assert_eq!(res.sources[0].topics.len(), 1);
+        assert_eq!(res.contacts.len(), 1);
+        assert!(res.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn slow_topics_branch_times_out_into_warning() {
+        let res = get(Duration::from_secs(5), DELAY * 2, Calls::default()).await;
+
+        assert_eq!(res.sources.len(), 1);
+        assert!(res.sources[0].topics.is_empty());
+        assert_eq!(res.warnings[0].code, "TOPICS_UNAVAILABLE");
+    }
+}
@@ -0,0 +1,11 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct UsersSettings {
+    #[serde(with = "humantime_serde")]
+    pub branch_timeout: Duration,
+    pub default_limit: usize,
+    pub max_limit: NonZeroUsize,
+}
@@ -4,8 +4,6 @@ mod app;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    bzd_lib::tracing::init()?;
-
     app::run().await?;
 
     Ok(())